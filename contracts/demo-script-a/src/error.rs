@@ -1,9 +1,10 @@
 use ckb_std::error::SysError;
+use registry_common::error::DEMO_SCRIPT_A_CODES;
 
-/// Error
+/// Error, the codes are in `DEMO_SCRIPT_A_CODES`, see `registry_common::error`
 #[repr(i8)]
 pub enum Error {
-    IndexOutOfBound = 1,
+    IndexOutOfBound = DEMO_SCRIPT_A_CODES.start,
     ItemMissing,
     LengthNotEnough,
     Encoding,
@@ -20,7 +21,7 @@ impl From<SysError> for Error {
             ItemMissing => Self::ItemMissing,
             LengthNotEnough(_) => Self::LengthNotEnough,
             Encoding => Self::Encoding,
-            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+            err => panic!("unexpected sys error {:?}", err),
         }
     }
 }
@@ -1,24 +1,28 @@
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::result::Result;
 
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 // Import CKB syscalls and structures
 // https://docs.rs/ckb-std/
 use ckb_std::{
     ckb_constants::Source,
     debug,
-    high_level::{decode_hex, load_script, load_witness},
+    high_level::{load_script, load_witness},
 };
+use registry_common::argv::WrapperArgv;
 
 use crate::error::Error;
 
 pub fn main() -> Result<(), Error> {
-    let (script_args, witness) = load_script_args_and_witness()?;
-    validate(script_args, witness)
+    let (script_args, witnesses) = load_script_args_and_witnesses()?;
+    for witness in witnesses {
+        validate(&script_args, witness)?;
+    }
+    Ok(())
 }
 
-fn validate(script_args: Vec<u8>, mut witness: Vec<u8>) -> Result<(), Error> {
+fn validate(script_args: &[u8], mut witness: Vec<u8>) -> Result<(), Error> {
     debug!("script_args is {:?}", script_args);
     debug!("witness is {:?}", witness);
     witness.reverse();
@@ -29,20 +33,26 @@ fn validate(script_args: Vec<u8>, mut witness: Vec<u8>) -> Result<(), Error> {
     }
 }
 
-fn load_script_args_and_witness() -> Result<(Vec<u8>, Vec<u8>), Error> {
-    if ckb_std::env::argv().len() == 0 {
-        Ok((
+// when executed by lock-wrapper, the argv carries the args and the witness index of each proof, see
+// `registry_common::argv` for the layouts
+fn load_script_args_and_witnesses() -> Result<(Vec<u8>, Vec<Vec<u8>>), Error> {
+    if ckb_std::env::argv().is_empty() {
+        return Ok((
             load_script()?.args().raw_data().to_vec(),
-            load_witness(0, Source::GroupInput)?,
-        ))
-    } else if ckb_std::env::argv().len() == 2 {
-        let script_args = decode_hex(&ckb_std::env::argv()[0])?;
-        let witness_index =
-            u16::from_le_bytes(decode_hex(&ckb_std::env::argv()[1])?.try_into().unwrap());
-        let witness = load_witness(witness_index as usize, Source::Input)?;
+            vec![load_witness(0, Source::GroupInput)?],
+        ));
+    }
 
-        Ok((script_args, witness))
-    } else {
-        Err(Error::WrongArgv)
+    let wrapper_argv = WrapperArgv::decode(ckb_std::env::argv().iter().map(|arg| &**arg))
+        .map_err(|_| Error::WrongArgv)?;
+    debug!(
+        "wrapper script hash is {:?}",
+        wrapper_argv.wrapper_script_hash
+    );
+    debug!("group inputs are {:?}", wrapper_argv.group_inputs);
+    let mut witnesses = Vec::new();
+    for witness_index in wrapper_argv.witness_indexes {
+        witnesses.push(load_witness(witness_index as usize, Source::Input)?);
     }
+    Ok((wrapper_argv.args, witnesses))
 }
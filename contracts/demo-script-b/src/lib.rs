@@ -0,0 +1,22 @@
+//! The contract is built as a library so it can also be compiled natively by the simulator in
+//! `native-simulators/demo-script-b-sim`, `main.rs` only wraps it into the riscv binary.
+//!
+//! See `entry.rs` for the `main` function.
+//! See `error.rs` for the `Error` type.
+
+#![cfg_attr(not(feature = "native-simulator"), no_std)]
+
+extern crate alloc;
+
+// define modules
+mod entry;
+mod error;
+
+/// program entry
+pub fn program_entry() -> i8 {
+    // Call main function and return error code
+    match entry::main() {
+        Ok(_) => 0,
+        Err(err) => err as i8,
+    }
+}
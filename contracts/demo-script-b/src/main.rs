@@ -1,34 +1,15 @@
 //! Generated by capsule
 //!
-//! `main.rs` is used to define rust lang items and modules.
-//! See `entry.rs` for the `main` function.
-//! See `error.rs` for the `Error` type.
+//! `main.rs` is used to define rust lang items, the contract is defined in `lib.rs`.
 
 #![no_std]
 #![cfg_attr(not(test), no_main)]
-#![feature(lang_items)]
-#![feature(alloc_error_handler)]
-#![feature(panic_info_message)]
-
-// define modules
-mod entry;
-mod error;
-
-#[cfg(test)]
-extern crate alloc;
 
 #[cfg(not(test))]
 use ckb_std::default_alloc;
 #[cfg(not(test))]
+use demo_script_b::program_entry;
+#[cfg(not(test))]
 ckb_std::entry!(program_entry);
 #[cfg(not(test))]
 default_alloc!();
-
-/// program entry
-pub fn program_entry() -> i8 {
-    // Call main function and return error code
-    match entry::main() {
-        Ok(_) => 0,
-        Err(err) => err as i8,
-    }
-}
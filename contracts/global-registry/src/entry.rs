@@ -1,6 +1,10 @@
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::{ops::Deref, result::Result};
 
+// Import heap related library from `alloc`
+// https://doc.rust-lang.org/alloc/index.html
+use alloc::vec::Vec;
+
 // Import CKB syscalls and structures
 // https://docs.rs/ckb-std/
 use ckb_std::{
@@ -13,11 +17,21 @@ use ckb_std::{
     syscalls::{self, SysError},
 };
 
+use registry_common::{
+    hash::calc_init_hash,
+    linked_list::{check_linked_list, sort_ranges, MAX_NODES_PER_TX},
+    node::{
+        decode_range, decode_registry_args, is_registry_lock, Range, KEY_MAX, KEY_MIN, KEY_SIZE,
+        NO_OVERRIDE,
+    },
+};
+
 use crate::error::Error;
 
 pub fn main() -> Result<(), Error> {
     if is_init() {
-        validate_init_hash()
+        validate_init_hash()?;
+        validate_sentinel()
     } else {
         validate_linked_list()
     }
@@ -34,82 +48,115 @@ fn is_init() -> bool {
     }
 }
 
-// check if the init hash is correct, which is the hash of the first input and the index of the first output with the same type script
+// check if the init hash in the type args is correct, which is the hash of the first input and the index of the first
+// output with the same type script
 fn validate_init_hash() -> Result<(), Error> {
     let current_script = load_script()?;
+    let (init_hash, _, _) = decode_registry_args(current_script.args().raw_data().deref())
+        .map_err(|_| Error::InvalidInitHash)?;
     let first_input = load_input(0, Source::Input)?;
     let first_output_index = load_first_output_index()?;
-    let mut hash = [0; 32];
-    let mut blake2b = blake2b_rs::Blake2bBuilder::new(32)
-        .personal(b"ckb-default-hash")
-        .build();
-    blake2b.update(first_input.as_slice());
-    blake2b.update(&first_output_index.to_le_bytes());
-    blake2b.finalize(&mut hash);
-
-    if current_script.args().raw_data().deref() == hash {
+    let hash = calc_init_hash(first_input.as_slice(), first_output_index as u64);
+
+    if init_hash == hash {
         Ok(())
     } else {
         Err(Error::InvalidInitHash)
     }
 }
 
-// check if the linked list is valid
+// check if exactly one registry cell is created at initialization, and it is the sentinel node [0x00..00, 0xff..ff)
+// locked by the lock-wrapper in the type args for this registry, so the lock-wrapper cells can trust the registry
+fn validate_sentinel() -> Result<(), Error> {
+    let range = load_range(0, Source::GroupOutput)?.ok_or(Error::InvalidInitOutputCount)?;
+    match load_cell_lock(1, Source::GroupOutput) {
+        Err(SysError::IndexOutOfBound) => {}
+        _ => return Err(Error::InvalidInitOutputCount),
+    }
+
+    if range.start != KEY_MIN {
+        return Err(Error::InvalidSentinelStart);
+    }
+    if range.end != KEY_MAX {
+        return Err(Error::InvalidSentinelEnd);
+    }
+
+    if is_node_locked(0)? {
+        Ok(())
+    } else {
+        Err(Error::InvalidSentinelLock)
+    }
+}
+
+// check if the linked list is valid, the group outputs must re-partition the ranges covered by the group inputs, see
+// `check_linked_list`. The cells are sorted by start key before the check, so the order of the inputs and outputs in
+// the transaction doesn't matter
 fn validate_linked_list() -> Result<(), Error> {
-    let mut o = 0;
-    let inputs_lock_iter = QueryIter::new(load_cell_lock, Source::GroupInput);
-    for (i, script) in inputs_lock_iter.enumerate() {
-        if script.args().len() < 32 {
-            return Err(Error::InvalidArgsLength);
-        }
-        let mut input_start: [u8; 32] = script.args().raw_data()[0..32].try_into().unwrap();
+    let inputs = load_sorted_ranges(Source::GroupInput)?;
+    let outputs = load_sorted_ranges(Source::GroupOutput)?;
+    let inserted = check_linked_list(&inputs, &outputs).map_err(|_| Error::InvalidLinkedList)?;
+    inserted
+        .into_iter()
+        .try_for_each(|(index, _)| validate_inserted_config(index))?;
+    validate_node_locks(outputs.len())
+}
 
-        let data = load_cell_data(i, Source::GroupInput)?;
-        if data.len() < 32 {
-            return Err(Error::InvalidDataLength);
-        }
-        let input_end: [u8; 32] = data[0..32].try_into().unwrap();
-
-        loop {
-            match load_cell_lock(o, Source::GroupOutput) {
-                Ok(script) => {
-                    if script.args().len() < 32 {
-                        return Err(Error::InvalidArgsLength);
-                    }
-                    let output_start: [u8; 32] =
-                        script.args().raw_data()[0..32].try_into().unwrap();
-                    if output_start != input_start {
-                        return Err(Error::InvalidLinkedList);
-                    }
-
-                    let data = load_cell_data(o, Source::GroupOutput)?;
-                    if data.len() < 32 {
-                        return Err(Error::InvalidDataLength);
-                    }
-                    let output_end: [u8; 32] = data[0..32].try_into().unwrap();
-                    if output_end <= output_start {
-                        return Err(Error::InvalidLinkedList);
-                    }
-
-                    o += 1;
-                    if output_end != input_end {
-                        input_start = output_end;
-                    } else {
-                        break;
-                    }
-                }
-                Err(_) => {
-                    return Err(Error::InvalidLinkedList);
-                }
-            }
+// every group output must be locked like the sentinel, lock-wrapper skips the registry cells locked otherwise, so
+// such a node would leave the keys it covers without an owner
+fn validate_node_locks(outputs_count: usize) -> Result<(), Error> {
+    for index in 0..outputs_count {
+        if !is_node_locked(index)? {
+            return Err(Error::InvalidNodeLock);
         }
     }
+    Ok(())
+}
+
+// check if the group output is locked by the lock-wrapper in the type args for this registry, see `is_registry_lock`
+fn is_node_locked(index: usize) -> Result<bool, Error> {
+    let lock_script = load_cell_lock(index, Source::GroupOutput)?;
+    Ok(is_registry_lock(
+        lock_script.as_slice(),
+        &load_script()?.args().raw_data(),
+        &load_script_hash()?,
+    ))
+}
+
+// a node inserted by a split starts at a key no registry cell covered as its start, so no lock-wrapper group checks
+// its config. It must have no override, the wrapped script of its start key can propose a config later
+fn validate_inserted_config(index: usize) -> Result<(), Error> {
+    let data = load_cell_data(index, Source::GroupOutput)?;
+    if data[KEY_SIZE..] == NO_OVERRIDE {
+        Ok(())
+    } else {
+        Err(Error::InvalidInsertedConfig)
+    }
+}
 
-    // check if all the outputs are visited
-    match load_cell_lock(o, Source::GroupOutput) {
-        Err(SysError::IndexOutOfBound) => Ok(()),
-        _ => Err(Error::InvalidLinkedList),
+// load the ranges of all the registry cells in the source with their indexes and sort them by start key,
+// the number of cells is limited to keep the cycles of sorting bounded
+fn load_sorted_ranges(source: Source) -> Result<Vec<(usize, Range)>, Error> {
+    let mut ranges = Vec::new();
+    while let Some(range) = load_range(ranges.len(), source)? {
+        if ranges.len() == MAX_NODES_PER_TX {
+            return Err(Error::TooManyNodes);
+        }
+        ranges.push((ranges.len(), range));
     }
+    sort_ranges(&mut ranges);
+    Ok(ranges)
+}
+
+// load the range [start, end) of a registry cell, start is stored in the lock args and end is stored in the data
+fn load_range(index: usize, source: Source) -> Result<Option<Range>, Error> {
+    let script = match load_cell_lock(index, source) {
+        Ok(script) => script,
+        Err(SysError::IndexOutOfBound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let data = load_cell_data(index, source)?;
+    let range = decode_range(&script.args().raw_data(), &data)?;
+    Ok(Some(range))
 }
 
 fn load_first_output_index() -> Result<usize, Error> {
@@ -1,9 +1,9 @@
-use ckb_std::error::SysError;
+use registry_common::{error::GLOBAL_REGISTRY_CODES, node::LayoutError};
 
-/// Error
+/// Error, the codes are in `GLOBAL_REGISTRY_CODES`, see `registry_common::error`
 #[repr(i8)]
 pub enum Error {
-    IndexOutOfBound = 1,
+    IndexOutOfBound = GLOBAL_REGISTRY_CODES.start,
     ItemMissing,
     LengthNotEnough,
     Encoding,
@@ -12,17 +12,22 @@ pub enum Error {
     InvalidDataLength,
     InvalidArgsLength,
     InvalidLinkedList,
+    InvalidInitOutputCount,
+    InvalidSentinelStart,
+    InvalidSentinelEnd,
+    TooManyNodes,
+    InvalidSentinelLock,
+    InvalidInsertedConfig,
+    InvalidNodeLock,
 }
 
-impl From<SysError> for Error {
-    fn from(err: SysError) -> Self {
-        use SysError::*;
+registry_common::impl_from_sys_error!(Error);
+
+impl From<LayoutError> for Error {
+    fn from(err: LayoutError) -> Self {
         match err {
-            IndexOutOfBound => Self::IndexOutOfBound,
-            ItemMissing => Self::ItemMissing,
-            LengthNotEnough(_) => Self::LengthNotEnough,
-            Encoding => Self::Encoding,
-            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+            LayoutError::InvalidArgs => Self::InvalidArgsLength,
+            LayoutError::InvalidData | LayoutError::InvalidConfig => Self::InvalidDataLength,
         }
     }
 }
@@ -0,0 +1,46 @@
+// Decoding of the layouts read by lock-wrapper, every malformed layout is turned into its own error code instead of
+// panicking on slicing, see schemas/registry.mol for the layouts.
+
+use core::result::Result;
+
+use ckb_std::ckb_types::{core::ScriptHashType, packed::WitnessArgs, prelude::*};
+use registry_common::{
+    node::{self, Node},
+    schema::{WrapperWitness, WrapperWitnessUnion},
+};
+
+use crate::error::Error;
+
+// lock args: the global registry type script hash and the start key, InvalidArgsLength if malformed
+pub fn decode_lock_args(lock_args: &[u8]) -> Result<([u8; 32], [u8; 32]), Error> {
+    Ok(node::decode_lock_args(lock_args)?)
+}
+
+// registry cell: the start key in the lock args, the end key and the config payload in the data,
+// InvalidDataLength if the end key is truncated and InvalidNodeConfig if the payload is malformed
+pub fn decode_node(lock_args: &[u8], data: &[u8]) -> Result<Node, Error> {
+    Ok(Node::from_slices(lock_args, data)?)
+}
+
+// witness: a WitnessArgs (InvalidWitnessFormat) with a WrapperWitness in the lock field (InvalidWrapperWitness),
+// returns None if the lock field is absent
+pub fn decode_wrapper_witness(witness: &[u8]) -> Result<Option<WrapperWitnessUnion>, Error> {
+    let witness_args = WitnessArgs::from_slice(witness).map_err(|_| Error::InvalidWitnessFormat)?;
+    match witness_args.lock().to_opt() {
+        Some(lock) => WrapperWitness::from_compatible_slice(&lock.raw_data())
+            .map(|witness| Some(witness.to_enum()))
+            .map_err(|_| Error::InvalidWrapperWitness),
+        None => Ok(None),
+    }
+}
+
+// hash type of the wrapped script: data, type, data1 or data2, InvalidWrappedScriptHashType otherwise
+pub fn decode_hash_type(hash_type: u8) -> Result<ScriptHashType, Error> {
+    match hash_type {
+        0 => Ok(ScriptHashType::Data),
+        1 => Ok(ScriptHashType::Type),
+        2 => Ok(ScriptHashType::Data1),
+        4 => Ok(ScriptHashType::Data2),
+        _ => Err(Error::InvalidWrappedScriptHashType),
+    }
+}
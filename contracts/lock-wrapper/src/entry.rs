@@ -1,44 +1,56 @@
 // Import from `core` instead of from `std` since we are in no-std mode
-use core::{cmp::Ordering, result::Result};
+use core::{ffi::CStr, result::Result};
 
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
-use alloc::vec::Vec;
+use alloc::{format, string::String, vec, vec::Vec};
 
 // Import CKB syscalls and structures
 // https://docs.rs/ckb-std/
 use ckb_std::{
     ckb_constants::Source,
-    ckb_types::{
-        core::ScriptHashType,
-        packed::{Script, ScriptReader},
-        prelude::*,
-    },
+    ckb_types::{core::ScriptHashType, packed::Script, prelude::*},
     debug,
     high_level::{
-        encode_hex, exec_cell, load_cell, load_cell_data, load_cell_lock, load_cell_type_hash,
-        load_script, load_witness, QueryIter,
+        exec_cell, load_cell_data, load_cell_lock, load_cell_lock_hash, load_cell_type_hash,
+        load_header, load_input_since, load_script, load_script_hash, load_witness, spawn_cell,
+        QueryIter,
+    },
+    syscalls::{self, SysError},
+};
+use registry_common::{
+    argv::{WrapperArgv, ARGV_VERSION_LEGACY},
+    hash::calc_script_hash,
+    node::{
+        check_request_since, decode_range, is_config_carried, is_recovery_requested, LayoutError,
+        Lookup, Node, RequestSince, PENDING_DELAY,
     },
+    schema::{Byte32Vec, WrappedProof, WrappedProofVec, WrapperWitnessUnion},
+    since::absolute_since_after,
 };
 
-use crate::error::Error;
+use crate::{
+    decode::{decode_hash_type, decode_lock_args, decode_node, decode_wrapper_witness},
+    error::Error,
+};
 
 pub fn main() -> Result<(), Error> {
     let current_script = load_script()?;
-    if current_script.args().len() != 64 {
-        return Err(Error::InvalidArgsLength);
-    }
+    let (global_registry_script_hash, wrapped_script_hash) =
+        decode_lock_args(&current_script.args().raw_data())?;
 
-    if is_delegate_to_wrapped(&current_script) {
-        exec_wrapped_script(&current_script)
+    if is_delegate_to_wrapped(global_registry_script_hash) {
+        exec_wrapped_script(
+            &current_script,
+            global_registry_script_hash,
+            wrapped_script_hash,
+        )
     } else {
-        validate_config_value(&current_script)
+        validate_config_value(&current_script, global_registry_script_hash)
     }
 }
 
-fn is_delegate_to_wrapped(current_script: &Script) -> bool {
-    let global_registry_script_hash: [u8; 32] =
-        current_script.args().raw_data()[0..32].try_into().unwrap();
+fn is_delegate_to_wrapped(global_registry_script_hash: [u8; 32]) -> bool {
     let inputs_type_hashes = QueryIter::new(load_cell_type_hash, Source::GroupInput);
     inputs_type_hashes.into_iter().all(|i| match i {
         Some(hash) => hash != global_registry_script_hash,
@@ -46,145 +58,498 @@ fn is_delegate_to_wrapped(current_script: &Script) -> bool {
     })
 }
 
-fn exec_wrapped_script(current_script: &Script) -> Result<(), Error> {
-    let global_registry_script_hash: [u8; 32] =
-        current_script.args().raw_data()[0..32].try_into().unwrap();
-    let wrapped_script_hash: [u8; 32] =
-        current_script.args().raw_data()[32..64].try_into().unwrap();
+fn exec_wrapped_script(
+    current_script: &Script,
+    global_registry_script_hash: [u8; 32],
+    wrapped_script_hash: [u8; 32],
+) -> Result<(), Error> {
+    let node = find_registry_node(
+        current_script,
+        global_registry_script_hash,
+        wrapped_script_hash,
+    )?;
+    // the registry cell is a cell dep, only an anchored pending config can be activated
+    let inputs_since = load_inputs_since();
+    match node.range.lookup(&wrapped_script_hash) {
+        Lookup::Start if node.is_pending_activated(&inputs_since, None) => {
+            exec_wrapped_script_inner(node.active_wrapped_script_hash(&inputs_since, None))
+        }
+        Lookup::Start => exec_current_wrapped_scripts(&node),
+        Lookup::Inside => exec_wrapped_script_inner(wrapped_script_hash),
+        Lookup::Outside => Err(Error::InvalidCellDepRef),
+    }
+}
 
-    let cell_dep_type_hash = load_cell_type_hash(0, Source::CellDep)?;
-    if cell_dep_type_hash
-        .map(|h| h == global_registry_script_hash)
-        .unwrap_or_default()
-    {
-        let cell_dep_lock_script = load_cell_lock(0, Source::CellDep)?;
+// search all the cell deps for the registry node covering the wrapped script hash, registry cells which are not locked
+// by the same lock-wrapper code or whose lock args name another registry are skipped
+fn find_registry_node(
+    current_script: &Script,
+    global_registry_script_hash: [u8; 32],
+    wrapped_script_hash: [u8; 32],
+) -> Result<Node, Error> {
+    let mut found_registry_cell = false;
+    let cell_deps_type_hashes = QueryIter::new(load_cell_type_hash, Source::CellDep);
+    for (index, type_hash) in cell_deps_type_hashes.enumerate() {
+        if type_hash != Some(global_registry_script_hash) {
+            continue;
+        }
+        found_registry_cell = true;
+
+        let cell_dep_lock_script = load_cell_lock(index, Source::CellDep)?;
         if cell_dep_lock_script.code_hash().as_bytes() != current_script.code_hash().as_bytes()
             || cell_dep_lock_script.hash_type() != current_script.hash_type()
-            || cell_dep_lock_script.args().len() != 64
         {
-            return Err(Error::InvalidCellDepRef);
+            continue;
         }
 
-        let data = load_cell_data(0, Source::CellDep)?;
-        if data.len() != 64 {
-            return Err(Error::InvalidDataLength);
+        let args = cell_dep_lock_script.args().raw_data();
+        let data = load_cell_data(index, Source::CellDep)?;
+        let range = match decode_range(&args, &data) {
+            Ok(range) => range,
+            Err(LayoutError::InvalidArgs) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let (registry_hash, _) = decode_lock_args(&args)?;
+        if registry_hash != global_registry_script_hash {
+            continue;
         }
-
-        let start: [u8; 32] = cell_dep_lock_script.args().raw_data()[32..64]
-            .try_into()
-            .unwrap();
-        match start.cmp(&wrapped_script_hash) {
-            Ordering::Equal => {
-                let config_wrapped_script_hash: [u8; 32] = data[32..64].try_into().unwrap();
-                exec_wrapped_script_inner(config_wrapped_script_hash)
-            }
-            Ordering::Less => {
-                let end: [u8; 32] = data[0..32].try_into().unwrap();
-                if end >= wrapped_script_hash {
-                    exec_wrapped_script_inner(wrapped_script_hash)
-                } else {
-                    return Err(Error::InvalidCellDepRef);
-                }
-            }
-            Ordering::Greater => {
-                return Err(Error::InvalidCellDepRef);
-            }
+        if range.lookup(&wrapped_script_hash) != Lookup::Outside {
+            return decode_node(&args, &data);
         }
+    }
+
+    if found_registry_cell {
+        Err(Error::InvalidCellDepRef)
     } else {
         Err(Error::InvalidCellDepTypeScript)
     }
 }
 
-fn validate_config_value(current_script: &Script) -> Result<(), Error> {
-    let global_registry_script_hash: [u8; 32] =
-        current_script.args().raw_data()[0..32].try_into().unwrap();
-    let inputs_type_hashes = QueryIter::new(load_cell_type_hash, Source::Input);
+// the registry input of the current group is the node starting at the start key in the lock args, its successor is
+// the registry output locked by the same lock script, so registry outputs can be placed at any index and the nodes of
+// several users can be updated in one transaction, each of them is validated by its own lock-wrapper group
+fn validate_config_value(
+    current_script: &Script,
+    global_registry_script_hash: [u8; 32],
+) -> Result<(), Error> {
+    // the registry cell is the only input of the group, the other cells of the start key would be unlocked without a
+    // proof when the config is carried
+    let inputs_index = find_registry_cells(Source::GroupInput, global_registry_script_hash, None)?;
+    let inputs_count = QueryIter::new(load_input_since, Source::GroupInput).count();
+    let input_index = match inputs_index.as_slice() {
+        [index] if inputs_count == 1 => *index,
+        _ => return Err(Error::InvalidInputCount),
+    };
 
-    let inputs_index: Vec<usize> = inputs_type_hashes
-        .enumerate()
-        .filter_map(|(index, i)| match i {
-            Some(hash) => {
-                if hash == global_registry_script_hash {
-                    Some(index)
-                } else {
-                    None
-                }
-            }
-            None => None,
-        })
-        .collect();
+    let args = current_script.args().raw_data();
+    let input_node = decode_node(&args, &load_cell_data(input_index, Source::GroupInput)?)?;
+    let outputs_index = find_registry_cells(
+        Source::Output,
+        global_registry_script_hash,
+        Some(current_script),
+    )?;
+    let output_index = match outputs_index.as_slice() {
+        // the node is merged into its predecessor, global-registry checks the ranges
+        [] => {
+            validate_merge(
+                current_script,
+                global_registry_script_hash,
+                input_node.range.start,
+            )?;
+            return validate_node_removal(&input_node, input_index);
+        }
+        [index] => *index,
+        _ => return Err(Error::InvalidOutputLockScript),
+    };
 
-    if inputs_index.len() != 1 {
-        return Err(Error::InvalidInputCount);
+    let output_node = decode_node(&args, &load_cell_data(output_index, Source::Output)?)?;
+    validate_request_since(&input_node, &output_node, input_index)?;
+    if is_config_carried(&input_node.config, &output_node.config) {
+        // if config value is not changed, skip validation, the requests may be anchored
+        return Ok(());
     }
 
-    let index = inputs_index[0];
-
-    let output = load_cell(index, Source::Output)?;
-    if current_script.as_bytes() != output.lock().as_bytes() {
-        return Err(Error::InvalidOutputLockScript);
+    if let Some(WrapperWitnessUnion::GuardianProof(guardian_proof)) = load_wrapper_witness(0)? {
+        // the guardians rotate the config when the wrapped script is lost
+        let since = load_input_since(input_index, Source::GroupInput)?;
+        return validate_guardian_recovery(
+            &input_node,
+            &output_node,
+            since,
+            guardian_proof.proofs(),
+        );
     }
 
-    let input_data = load_cell_data(index, Source::Input)?;
-    let output_data = load_cell_data(index, Source::Output)?;
-    if input_data[32..64] == output_data[32..64] {
-        // if config value is not changed, skip validation
-        return Ok(());
+    let inputs_since = load_inputs_since();
+    let registry_input_since = Some(load_input_since(input_index, Source::GroupInput)?);
+    if input_node.is_pending_activated(&inputs_since, registry_input_since) {
+        // the pending config is activated, its wrapped script takes over the node and can promote it
+        exec_wrapped_script_inner(
+            input_node.active_wrapped_script_hash(&inputs_since, registry_input_since),
+        )
+    } else if input_node.config.wrapped_script_hash().as_slice()
+        == output_node.config.wrapped_script_hash().as_slice()
+        && input_node.config.threshold_scripts().as_slice()
+            == output_node.config.threshold_scripts().as_slice()
+    {
+        // the current wrapped scripts can propose or cancel a pending config and update the app data
+        exec_current_wrapped_scripts(&input_node)
     } else {
-        // else, verify by executing wrapped script
-        exec_wrapped_script_inner(input_data[32..64].try_into().unwrap())
+        // the owners of the node can only be changed through an activated pending config
+        Err(Error::InvalidConfigChange)
     }
 }
 
-fn exec_wrapped_script_inner(wrapped_script_hash: [u8; 32]) -> Result<(), Error> {
-    let witness = load_witness(0, Source::GroupInput)?;
-    let (wrapped_script, wrapped_script_witness_index) = parse_witness(&witness)?;
-    let script_hash = calc_script_hash(&wrapped_script);
-    if script_hash != wrapped_script_hash {
-        return Err(Error::InvalidWrappedScriptHash);
+// the registry output covering the start key of a removed node must be locked by lock-wrapper of the same registry and
+// start before it, so the range is merged into the predecessor instead of being handed to another lock
+fn validate_merge(
+    current_script: &Script,
+    global_registry_script_hash: [u8; 32],
+    start: [u8; 32],
+) -> Result<(), Error> {
+    for index in find_registry_cells(Source::Output, global_registry_script_hash, None)? {
+        let lock_script = load_cell_lock(index, Source::Output)?;
+        if lock_script.code_hash().as_bytes() != current_script.code_hash().as_bytes()
+            || lock_script.hash_type() != current_script.hash_type()
+        {
+            continue;
+        }
+        let args = lock_script.args().raw_data();
+        let range = match decode_range(&args, &load_cell_data(index, Source::Output)?) {
+            Ok(range) => range,
+            Err(_) => continue,
+        };
+        let (registry_hash, _) = decode_lock_args(&args)?;
+        if registry_hash == global_registry_script_hash && range.lookup(&start) == Lookup::Inside {
+            return Ok(());
+        }
     }
+    Err(Error::InvalidOutputLockScript)
+}
 
-    let hash_type = if wrapped_script.hash_type().as_slice() == &[1] {
-        ScriptHashType::Type
+// the config of a removed node is lost, so its owners must consent, which are the pending ones once activated
+fn validate_node_removal(node: &Node, input_index: usize) -> Result<(), Error> {
+    let inputs_since = load_inputs_since();
+    let registry_input_since = Some(load_input_since(input_index, Source::GroupInput)?);
+    if node.is_pending_activated(&inputs_since, registry_input_since) {
+        exec_wrapped_script_inner(
+            node.active_wrapped_script_hash(&inputs_since, registry_input_since),
+        )
     } else {
-        ScriptHashType::Data
-    };
+        exec_current_wrapped_scripts(node)
+    }
+}
+
+// a pending config or a recovery is requested with a zero since, the first transaction spending the cell holding it
+// anchors it to the absolute since its delay after the block of the cell, so the delay is counted from the request
+// even if the node is split meanwhile
+fn validate_request_since(
+    input_node: &Node,
+    output_node: &Node,
+    input_index: usize,
+) -> Result<(), Error> {
+    let pending = check_request_since(input_node.pending_request(), output_node.pending_request());
+    validate_anchor(pending, input_index, PENDING_DELAY)?;
+    // the guardian delay is part of the request, a request of another delay is a new one, so only a carried request
+    // is anchored with the delay of the input
+    let recovery = check_request_since(
+        input_node.recovery_request(),
+        output_node.recovery_request(),
+    );
+    let delay = input_node.recovery_request().map_or(0, |(delay, _)| delay);
+    validate_anchor(recovery, input_index, delay)
+}
 
-    let arg0 = encode_hex(&wrapped_script.args().raw_data().to_vec());
-    let arg1 = encode_hex(&wrapped_script_witness_index.to_le_bytes());
-    debug!("arg0: {:?}", arg0);
-    debug!("arg1: {:?}", arg1);
+fn validate_anchor(request: RequestSince, input_index: usize, delay: u64) -> Result<(), Error> {
+    match request {
+        RequestSince::Valid => Ok(()),
+        RequestSince::Anchored(since) if since == load_anchor(input_index, delay)? => Ok(()),
+        _ => Err(Error::InvalidRequestSince),
+    }
+}
+
+// the absolute since `delay` after the block of the registry input, its header must be in the header deps
+fn load_anchor(input_index: usize, delay: u64) -> Result<u64, Error> {
+    let header = load_header(input_index, Source::GroupInput)?.raw();
+    absolute_since_after(
+        delay,
+        header.number().unpack(),
+        header.epoch().unpack(),
+        header.timestamp().unpack(),
+    )
+    .ok_or(Error::InvalidRequestSince)
+}
+
+// the current owners of the start key of the node, the threshold scripts if present, otherwise the wrapped script
+// which is the one in the lock args if the node has no override
+fn exec_current_wrapped_scripts(node: &Node) -> Result<(), Error> {
+    match node.config.threshold_scripts().to_opt() {
+        Some(threshold_scripts) => exec_threshold_scripts(
+            threshold_scripts.wrapped_script_hashes(),
+            threshold_scripts.threshold().as_slice()[0],
+        ),
+        None => exec_wrapped_script_inner(node.wrapped_script_hash()),
+    }
+}
 
-    exec_cell(
-        wrapped_script.code_hash().as_slice(),
-        hash_type,
-        &[&arg0, &arg1],
+// `threshold` of the guardians must succeed, they first request the recovery without changing anything else, then
+// once the request is activated the config can be changed in any way and the request is cleared
+fn validate_guardian_recovery(
+    input_node: &Node,
+    output_node: &Node,
+    since: u64,
+    proofs: WrappedProofVec,
+) -> Result<(), Error> {
+    let guardians = input_node
+        .config
+        .guardians()
+        .to_opt()
+        .ok_or(Error::MissingGuardianSet)?;
+    if input_node.recovery_request().is_none() {
+        if !is_recovery_requested(&input_node.config, &output_node.config) {
+            return Err(Error::InvalidRecovery);
+        }
+    } else if !input_node.is_recovery_activated(&load_inputs_since(), Some(since)) {
+        return Err(Error::GuardianDelayNotReached);
+    } else if output_node.config.recovery().is_some() {
+        return Err(Error::InvalidRecovery);
+    }
+    let proofs = verify_threshold_proofs(
+        guardians.guardian_hashes(),
+        guardians.threshold().as_slice()[0],
+        proofs,
     )?;
+    run_wrapped_scripts(&proofs)
+}
+
+// `threshold` of the scripts whose hashes are in the list must be proved by the multi proof of the first group input,
+// and all the proved scripts must succeed
+fn exec_threshold_scripts(hashes: Byte32Vec, threshold: u8) -> Result<(), Error> {
+    let multi_proof = match load_wrapper_witness(0)? {
+        Some(WrapperWitnessUnion::MultiProof(multi_proof)) => multi_proof,
+        _ => return Err(Error::InvalidWrapperWitness),
+    };
+    let proofs = verify_threshold_proofs(hashes, threshold, multi_proof.proofs())?;
+    run_wrapped_scripts(&proofs)
+}
+
+// each proof must be of a distinct script whose hash is in the list, and at least `threshold` proofs are presented,
+// a zero threshold is never reached
+fn verify_threshold_proofs(
+    hashes: Byte32Vec,
+    threshold: u8,
+    proofs: WrappedProofVec,
+) -> Result<Vec<WrappedProof>, Error> {
+    let hashes: Vec<[u8; 32]> = hashes.into_iter().map(Into::into).collect();
+    let mut proved_hashes = Vec::new();
+    let mut verified_proofs = Vec::new();
+    for proof in proofs.into_iter() {
+        let hash = calc_script_hash(proof.wrapped_script().as_slice());
+        if !hashes.contains(&hash) || proved_hashes.contains(&hash) {
+            report_failure(format!(
+                "invalid proof {} of the multi proof",
+                verified_proofs.len()
+            ));
+            return Err(Error::InvalidMultiProof);
+        }
+        proved_hashes.push(hash);
+        verified_proofs.push(proof);
+    }
+
+    if threshold == 0 || verified_proofs.len() < threshold as usize {
+        return Err(Error::ThresholdNotReached);
+    }
+    Ok(verified_proofs)
+}
+
+// the since values of the group inputs, the transaction can only be committed after all of them
+fn load_inputs_since() -> Vec<u64> {
+    QueryIter::new(load_input_since, Source::GroupInput).collect()
+}
+
+// find the indexes of the registry cells in the source, optionally only the ones locked by the given lock script
+fn find_registry_cells(
+    source: Source,
+    global_registry_script_hash: [u8; 32],
+    lock_script: Option<&Script>,
+) -> Result<Vec<usize>, Error> {
+    let mut indexes = Vec::new();
+    let type_hashes = QueryIter::new(load_cell_type_hash, source);
+    for (index, type_hash) in type_hashes.enumerate() {
+        if type_hash != Some(global_registry_script_hash) {
+            continue;
+        }
+        if let Some(lock_script) = lock_script {
+            if load_cell_lock(index, source)?.as_slice() != lock_script.as_slice() {
+                continue;
+            }
+        }
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+fn exec_wrapped_script_inner(wrapped_script_hash: [u8; 32]) -> Result<(), Error> {
+    let (proof, witness_indexes) = load_wrapped_script_proofs(wrapped_script_hash)?;
+    run_wrapped_script(&proof, &witness_indexes)
+}
+
+// run each proved script with the witness index of its own proof
+fn run_wrapped_scripts(proofs: &[WrappedProof]) -> Result<(), Error> {
+    if proofs.len() > 1 && !is_spawn_supported()? {
+        return Err(Error::SpawnRequired);
+    }
+    for proof in proofs {
+        let witness_index: u16 = proof.witness_index().as_reader().into();
+        run_wrapped_script(proof, &[witness_index])?;
+    }
     Ok(())
 }
 
-fn parse_witness(witness: &[u8]) -> Result<(Script, u16), Error> {
-    // 2 (witness_index) + 53 (min script size)
-    if witness.len() < 55 {
-        return Err(Error::InvalidWitnessFormat);
+// run the wrapped script of the proof with the witness indexes, the argv layout is selected by the proof
+fn run_wrapped_script(proof: &WrappedProof, witness_indexes: &[u16]) -> Result<(), Error> {
+    let wrapped_script = proof.wrapped_script();
+    let hash_type = decode_hash_type(wrapped_script.hash_type().as_slice()[0])?;
+    let code_hash = wrapped_script.code_hash();
+
+    let mut wrapper_argv = load_wrapper_argv(proof.argv_version().as_slice()[0])?;
+    wrapper_argv.args = wrapped_script.args().raw_data().to_vec();
+
+    if is_spawn_supported()? {
+        // spawn the wrapped script for each proof with the witness index of that proof, the failing proof can be
+        // reported and the wrapper keeps running after the wrapped script returns
+        for witness_index in witness_indexes {
+            wrapper_argv.witness_indexes = vec![*witness_index];
+            let argv = wrapper_argv.encode()?;
+            let argv: Vec<&CStr> = argv.iter().map(|arg| arg.as_c_str()).collect();
+            debug!("argv: {:?}", argv);
+            spawn_wrapped_script(code_hash.as_slice(), hash_type, &argv).inspect_err(|_| {
+                report_failure(format!(
+                    "the wrapped script failed on witness {}",
+                    witness_index
+                ));
+            })?;
+        }
+        Ok(())
+    } else {
+        // exec the wrapped script once with the witness indexes of all the proofs
+        wrapper_argv.witness_indexes = witness_indexes.to_vec();
+        let argv = wrapper_argv.encode()?;
+        let argv: Vec<&CStr> = argv.iter().map(|arg| arg.as_c_str()).collect();
+        debug!("argv: {:?}", argv);
+
+        exec_cell(code_hash.as_slice(), hash_type, &argv)?;
+        Ok(())
+    }
+}
+
+// the group context passed to the wrapped script, the legacy layout passes none of it
+fn load_wrapper_argv(version: u8) -> Result<WrapperArgv, Error> {
+    let mut wrapper_argv = WrapperArgv {
+        version,
+        ..Default::default()
+    };
+    if version != ARGV_VERSION_LEGACY {
+        let wrapper_script_hash = load_script_hash()?;
+        wrapper_argv.wrapper_script_hash = wrapper_script_hash;
+        wrapper_argv.group_inputs = QueryIter::new(load_cell_lock_hash, Source::Input)
+            .enumerate()
+            .filter(|(_, lock_hash)| *lock_hash == wrapper_script_hash)
+            .map(|(index, _)| index as u32)
+            .collect();
+    }
+    Ok(wrapper_argv)
+}
+
+// spawn is available since VM version 2, exec is used before that which replaces the wrapper process. The VM version
+// is selected by the hash type of lock-wrapper: data runs on VM version 0 which has neither exec, spawn nor the
+// vm_version syscall, data1 and data2 pin VM version 1 and 2, and type runs on the latest version
+fn is_spawn_supported() -> Result<bool, Error> {
+    match decode_hash_type(load_script()?.hash_type().as_slice()[0])? {
+        ScriptHashType::Data => Err(Error::UnsupportedVmVersion),
+        ScriptHashType::Data1 => Ok(false),
+        ScriptHashType::Data2 => Ok(true),
+        ScriptHashType::Type => Ok(syscalls::vm_version()? >= 2),
+    }
+}
+
+// spawn the wrapped script and wait for its exit code
+fn spawn_wrapped_script(
+    code_hash: &[u8],
+    hash_type: ScriptHashType,
+    argv: &[&CStr],
+) -> Result<(), Error> {
+    let pid = spawn_cell(code_hash, hash_type, argv, &[])?;
+    let exit_code = syscalls::wait(pid)?;
+    if exit_code == 0 {
+        Ok(())
+    } else {
+        debug!("wrapped script exit code: {}", exit_code);
+        Err(Error::WrappedScriptFailed)
     }
-    let wrapped_script_witness_index = u16::from_le_bytes(witness[0..2].try_into().unwrap());
-    let wrapped_script_data = witness[2..].to_vec();
-    match ScriptReader::verify(&wrapped_script_data, false) {
-        Ok(()) => {
-            let wrapped_script = Script::new_unchecked(wrapped_script_data.into());
-            Ok((wrapped_script, wrapped_script_witness_index))
+}
+
+// the first group input must present a proof of the wrapped script, the other group inputs can either present
+// their own proofs of the same argv version or share the first one, all the proofs are verified and the witness
+// indexes are collected
+fn load_wrapped_script_proofs(
+    wrapped_script_hash: [u8; 32],
+) -> Result<(WrappedProof, Vec<u16>), Error> {
+    let inputs_count = QueryIter::new(load_input_since, Source::GroupInput).count();
+    let mut first_proof: Option<WrappedProof> = None;
+    let mut witness_indexes = Vec::new();
+    for index in 0..inputs_count {
+        let proof = match load_wrapper_witness(index) {
+            Ok(Some(WrapperWitnessUnion::WrappedProof(proof))) => proof,
+            Ok(Some(_)) => {
+                report_failure(format!("unexpected multi proof of group input {}", index));
+                return Err(Error::InvalidWrapperWitness);
+            }
+            Ok(None) if index > 0 => continue,
+            Ok(None) => return Err(Error::MissingWrapperWitness),
+            Err(err) => {
+                report_failure(format!("invalid wrapper witness of group input {}", index));
+                return Err(err);
+            }
+        };
+
+        if calc_script_hash(proof.wrapped_script().as_slice()) != wrapped_script_hash {
+            report_failure(format!(
+                "invalid wrapped script hash of group input {}",
+                index
+            ));
+            return Err(Error::InvalidWrappedScriptHash);
+        }
+        // the wrapped script runs with the argv layout of the first proof
+        if matches!(&first_proof, Some(first) if first.argv_version() != proof.argv_version()) {
+            report_failure(format!("mixed argv version of group input {}", index));
+            return Err(Error::MixedArgvVersions);
         }
-        Err(_err) => Err(Error::InvalidWitnessFormat),
+        witness_indexes.push(proof.witness_index().as_reader().into());
+        first_proof.get_or_insert(proof);
     }
+
+    // the first group input always presents a proof
+    Ok((first_proof.unwrap(), witness_indexes))
+}
+
+// the exit code only tells which check failed, the failing proof is written with the debug syscall, which is kept in
+// release builds unlike `debug!`, so ckb-debugger and the nodes printing the script debug output show it
+fn report_failure(message: String) {
+    syscalls::debug(format!("lock-wrapper: {}", message));
 }
 
-fn calc_script_hash(script: &Script) -> [u8; 32] {
-    let mut hash = [0; 32];
-    let mut blake2b = blake2b_rs::Blake2bBuilder::new(32)
-        .personal(b"ckb-default-hash")
-        .build();
-    blake2b.update(script.as_slice());
-    blake2b.finalize(&mut hash);
-    hash
+// the wrapper witness is stored in the lock field of WitnessArgs, the type fields are left to the type scripts,
+// returns None if the group input does not present its own proof
+fn load_wrapper_witness(index: usize) -> Result<Option<WrapperWitnessUnion>, Error> {
+    let witness = match load_witness(index, Source::GroupInput) {
+        Ok(witness) => witness,
+        Err(SysError::IndexOutOfBound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if witness.is_empty() {
+        return Ok(None);
+    }
+
+    decode_wrapper_witness(&witness)
 }
@@ -1,9 +1,9 @@
-use ckb_std::error::SysError;
+use registry_common::{argv::ArgvError, error::LOCK_WRAPPER_CODES, node::LayoutError};
 
-/// Error
+/// Error, the codes are in `LOCK_WRAPPER_CODES`, see `registry_common::error`
 #[repr(i8)]
 pub enum Error {
-    IndexOutOfBound = 1,
+    IndexOutOfBound = LOCK_WRAPPER_CODES.start,
     ItemMissing,
     LengthNotEnough,
     Encoding,
@@ -16,17 +16,41 @@ pub enum Error {
     InvalidOutputLockScript,
     InvalidWitnessFormat,
     InvalidWrappedScriptHash,
+    InvalidNodeConfig,
+    WrappedScriptFailed,
+    InvalidWrappedScriptHashType,
+    InvalidWrapperWitness,
+    MissingWrapperWitness,
+    InvalidConfigChange,
+    MissingGuardianSet,
+    GuardianDelayNotReached,
+    InvalidMultiProof,
+    ThresholdNotReached,
+    SpawnRequired,
+    UnsupportedArgvVersion,
+    UnsupportedVmVersion,
+    InvalidRequestSince,
+    InvalidRecovery,
+    MixedArgvVersions,
 }
 
-impl From<SysError> for Error {
-    fn from(err: SysError) -> Self {
-        use SysError::*;
+registry_common::impl_from_sys_error!(Error);
+
+impl From<LayoutError> for Error {
+    fn from(err: LayoutError) -> Self {
+        match err {
+            LayoutError::InvalidArgs => Self::InvalidArgsLength,
+            LayoutError::InvalidData => Self::InvalidDataLength,
+            LayoutError::InvalidConfig => Self::InvalidNodeConfig,
+        }
+    }
+}
+
+impl From<ArgvError> for Error {
+    fn from(err: ArgvError) -> Self {
         match err {
-            IndexOutOfBound => Self::IndexOutOfBound,
-            ItemMissing => Self::ItemMissing,
-            LengthNotEnough(_) => Self::LengthNotEnough,
-            Encoding => Self::Encoding,
-            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+            ArgvError::UnsupportedVersion => Self::UnsupportedArgvVersion,
+            ArgvError::InvalidEncoding | ArgvError::InvalidLength => Self::Encoding,
         }
     }
 }
@@ -0,0 +1,54 @@
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{prelude::*, H256};
+use registry_sdk::{JsonCell, RegistryClient, RegistryState, ScriptCode};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// A deployed contract in the registry file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContractConfig {
+    pub code_hash: H256,
+    pub hash_type: json_types::ScriptHashType,
+    pub cell_dep: json_types::CellDep,
+}
+
+impl From<ContractConfig> for ScriptCode {
+    fn from(contract: ContractConfig) -> Self {
+        ScriptCode::new(
+            contract.code_hash.pack(),
+            contract.hash_type.into(),
+            contract.cell_dep.into(),
+        )
+    }
+}
+
+/// The registry file, the deployed contracts and the type args of the registry. The type args are absent until
+/// the registry is created by `init`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    pub global_registry: ContractConfig,
+    pub lock_wrapper: ContractConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init_hash: Option<H256>,
+}
+
+impl RegistryConfig {
+    /// A client of the registry tracking the live registry cells in the dump, the other cells are ignored
+    pub fn client(&self, cells: &[JsonCell]) -> Result<RegistryClient, Error> {
+        let init_hash = self.init_hash.clone().ok_or(Error::MissingInitHash)?;
+        let mut client = RegistryClient::new(
+            self.global_registry.clone().into(),
+            self.lock_wrapper.clone().into(),
+            init_hash.0,
+        );
+        let state = RegistryState::from_cells(
+            client.registry_type_script(),
+            cells.iter().cloned().map(Into::into),
+        );
+        for cell in state.cells() {
+            client.add_cell(cell.clone());
+        }
+        Ok(client)
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Sdk(registry_sdk::Error),
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// the registry file has no type args, it is not created by `init` yet
+    MissingInitHash,
+    /// the registry file already has type args
+    InitHashExists,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Sdk(err) => write!(f, "{}", err),
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Json(err) => write!(f, "invalid json: {}", err),
+            Error::MissingInitHash => write!(f, "the registry has no init hash, run init first"),
+            Error::InitHashExists => write!(f, "the registry is already initialized"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<registry_sdk::Error> for Error {
+    fn from(err: registry_sdk::Error) -> Self {
+        Error::Sdk(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
@@ -0,0 +1,211 @@
+//! The commands of `registry-cli`.
+//!
+//! The commands run offline: the registry file describes the deployed contracts and the type args of the
+//! registry, the live cells are read from a JSON dump of `JsonCell`s, and the unsigned transactions are written
+//! as the JSON transactions of the CKB RPC. The caller balances the capacity, signs the other inputs and sends
+//! the transactions.
+
+mod config;
+mod error;
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    bytes::Bytes,
+    packed::{CellDep, CellInput, OutPoint, Script, Transaction},
+    prelude::*,
+    H256,
+};
+use registry_sdk::{
+    registry_common::node::{decode_registry_args, Key, Lookup},
+    Issue, JsonCell, PreflightError, RegistryCell, RegistryClient, RegistryState,
+};
+use serde::Serialize;
+
+pub use config::{ContractConfig, RegistryConfig};
+pub use error::Error;
+
+/// The node lock-wrapper loads to unlock the cells of a key
+#[derive(Clone, Debug, Serialize)]
+pub struct LookupResult {
+    pub out_point: json_types::OutPoint,
+    pub start: H256,
+    pub end: H256,
+    /// the wrapped script unlocking the cells of the key, it is the activated pending one or the configured one if
+    /// the key is the start of the node, otherwise the key is delegated to itself. It is absent if the start key is
+    /// owned by threshold scripts
+    pub wrapped_script_hash: Option<H256>,
+    /// the scripts of which `threshold` must be proved to unlock the cells of the start key
+    pub threshold_scripts: Option<ThresholdScriptsResult>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ThresholdScriptsResult {
+    pub wrapped_script_hashes: Vec<H256>,
+    pub threshold: u8,
+}
+
+/// Creates a registry spending the first input, the returned registry file has the init hash of the new registry,
+/// which is derived the same way as `validate_init_hash`
+pub fn init(
+    contracts: &RegistryConfig,
+    first_input: OutPoint,
+) -> Result<(RegistryConfig, json_types::Transaction), Error> {
+    if contracts.init_hash.is_some() {
+        return Err(Error::InitHashExists);
+    }
+    let first_input = CellInput::new_builder()
+        .previous_output(first_input)
+        .build();
+    let (client, tx) = RegistryClient::init(
+        contracts.global_registry.clone().into(),
+        contracts.lock_wrapper.clone().into(),
+        first_input,
+    )?;
+    let (init_hash, _, _) = decode_registry_args(&client.registry_type_script().args().raw_data())
+        .expect("registry args");
+    let init_hash = H256(init_hash);
+    let registry = RegistryConfig {
+        init_hash: Some(init_hash),
+        ..contracts.clone()
+    };
+    Ok((registry, tx.data().into()))
+}
+
+/// Inserts the key without override, its own wrapped script proposes a config with `set-config` later
+pub fn insert(
+    registry: &RegistryConfig,
+    cells: &[JsonCell],
+    key: Key,
+) -> Result<json_types::Transaction, Error> {
+    let client = registry.client(cells)?;
+    Ok(client.insert_key(key)?.data().into())
+}
+
+/// Replaces the config payload of the node starting at the key, the wrapper witness proving the current wrapped
+/// script is added by the caller
+pub fn set_config(
+    registry: &RegistryConfig,
+    cells: &[JsonCell],
+    key: Key,
+    config: &[u8],
+) -> Result<json_types::Transaction, Error> {
+    let client = registry.client(cells)?;
+    Ok(client.update_config(key, config)?.data().into())
+}
+
+/// Finds the node covering the wrapped script hash and the scripts unlocking its cells in a transaction whose inputs
+/// have the given since, the owners are selected the same way as `exec_wrapped_script` of lock-wrapper
+pub fn lookup(
+    registry: &RegistryConfig,
+    cells: &[JsonCell],
+    wrapped_hash: Key,
+    since: u64,
+) -> Result<Option<LookupResult>, Error> {
+    let client = registry.client(cells)?;
+    let (cell, lookup) = match client.lookup(&wrapped_hash) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let node = cell.node()?;
+    let inputs_since = [since];
+    let (wrapped_script_hash, threshold_scripts) = match lookup {
+        Lookup::Start if node.is_pending_activated(&inputs_since, None) => (
+            Some(node.active_wrapped_script_hash(&inputs_since, None)),
+            None,
+        ),
+        Lookup::Start => match node.config.threshold_scripts().to_opt() {
+            Some(threshold_scripts) => (
+                None,
+                Some(ThresholdScriptsResult {
+                    wrapped_script_hashes: threshold_scripts
+                        .wrapped_script_hashes()
+                        .into_iter()
+                        .map(|hash| H256(hash.into()))
+                        .collect(),
+                    threshold: threshold_scripts.threshold().as_slice()[0],
+                }),
+            ),
+            None => (Some(node.wrapped_script_hash()), None),
+        },
+        _ => (Some(wrapped_hash), None),
+    };
+    Ok(Some(LookupResult {
+        out_point: cell.out_point.clone().into(),
+        start: H256(node.range.start),
+        end: H256(node.range.end),
+        wrapped_script_hash: wrapped_script_hash.map(H256),
+        threshold_scripts,
+    }))
+}
+
+/// Unlocks the lock-wrapper inputs of the key with the wrapped script, the cell deps of the wrapped script are
+/// added after the registry cell deps. The inner witness follows the inputs unless its index is given
+#[allow(clippy::too_many_arguments)]
+pub fn unlock(
+    registry: &RegistryConfig,
+    cells: &[JsonCell],
+    key: Key,
+    wrapped_script: &Script,
+    inputs: Vec<OutPoint>,
+    cell_deps: Vec<OutPoint>,
+    inner_witness_index: Option<u16>,
+    inner_witness: Bytes,
+) -> Result<json_types::Transaction, Error> {
+    let client = registry.client(cells)?;
+    let inputs: Vec<CellInput> = inputs
+        .into_iter()
+        .map(|out_point| CellInput::new_builder().previous_output(out_point).build())
+        .collect();
+    let inner_witness_index = inner_witness_index.unwrap_or(inputs.len() as u16);
+    let cell_deps: Vec<CellDep> = cell_deps
+        .into_iter()
+        .map(|out_point| CellDep::new_builder().out_point(out_point).build())
+        .collect();
+    let tx = client
+        .build_unlock_tx(
+            key,
+            wrapped_script,
+            inputs,
+            inner_witness_index,
+            inner_witness,
+        )?
+        .as_advanced_builder()
+        .cell_deps(cell_deps)
+        .build();
+    Ok(tx.data().into())
+}
+
+/// Checks the linked list rebuilt from the live cells of the registry
+pub fn verify(registry: &RegistryConfig, cells: &[JsonCell]) -> Result<Vec<Issue>, Error> {
+    let client = registry.client(cells)?;
+    let state = RegistryState::from_cells(
+        client.registry_type_script(),
+        cells.iter().cloned().map(Into::into),
+    );
+    Ok(state.issues())
+}
+
+/// Runs the checks of the contracts on the transaction, the dump resolves its inputs and cell deps
+pub fn verify_tx(
+    registry: &RegistryConfig,
+    cells: &[JsonCell],
+    tx: json_types::Transaction,
+) -> Result<Vec<PreflightError>, Error> {
+    let client = registry.client(cells)?;
+    let cells: Vec<RegistryCell> = cells.iter().cloned().map(Into::into).collect();
+    Ok(client.preflight(&Transaction::from(tx).into_view(), &cells))
+}
+
+/// Parses the hex of the command line arguments, the `0x` prefix is optional
+pub fn parse_hex(hex: &str) -> Result<Bytes, String> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    // `from_str_radix` accepts a leading sign, so every character is checked to be a hex digit first
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) || !hex.len().is_multiple_of(2) {
+        return Err("invalid hex".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<u8>, String>>()
+        .map(Bytes::from)
+}
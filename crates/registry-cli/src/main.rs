@@ -0,0 +1,289 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    bytes::Bytes,
+    packed::{OutPoint, Script},
+    prelude::*,
+};
+use clap::{Parser, Subcommand};
+use registry_cli::{parse_hex, Error, RegistryConfig};
+use registry_sdk::{
+    decode_exit_code,
+    registry_common::{hash::calc_script_hash, node::Key},
+    Contract, JsonCell,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Builds the transactions of a global registry offline
+#[derive(Parser)]
+#[command(name = "registry-cli")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates a registry spending the first input, and writes the registry file with its type args
+    Init {
+        /// the registry file with the deployed contracts, it has no type args yet
+        #[arg(long)]
+        contracts: PathBuf,
+        /// the first input of the transaction, as <tx_hash>:<index>
+        #[arg(long, value_parser = parse_out_point)]
+        first_input: OutPoint,
+        /// where to write the registry file of the new registry
+        #[arg(long)]
+        registry: PathBuf,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Inserts a key by splitting the node covering it
+    Insert {
+        #[command(flatten)]
+        state: State,
+        #[arg(long, value_parser = parse_key)]
+        key: Key,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Replaces the config payload of the node starting at the key
+    SetConfig {
+        #[command(flatten)]
+        state: State,
+        #[arg(long, value_parser = parse_key)]
+        key: Key,
+        /// the config payload, a wrapped script hash or a molecule encoded NodeConfig
+        #[arg(long, value_parser = parse_hex)]
+        config: Bytes,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Prints the node lock-wrapper loads for a wrapped script hash
+    Lookup {
+        #[command(flatten)]
+        state: State,
+        #[arg(long, value_parser = parse_key)]
+        key: Key,
+        /// the since of the unlocking inputs, an anchored pending config is activated once it is reached
+        #[arg(long, default_value_t = 0)]
+        since: u64,
+    },
+    /// Unlocks the lock-wrapper cells of a key with the wrapped script
+    Unlock {
+        #[command(flatten)]
+        state: State,
+        /// the key of the lock-wrapper cells, the hash of the wrapped script by default
+        #[arg(long, value_parser = parse_key)]
+        key: Option<Key>,
+        /// a JSON file with the wrapped script
+        #[arg(long)]
+        wrapped_script: PathBuf,
+        /// the lock-wrapper cells to unlock, as <tx_hash>:<index>
+        #[arg(long = "input", value_parser = parse_out_point, required = true)]
+        inputs: Vec<OutPoint>,
+        /// the cell deps of the wrapped script, as <tx_hash>:<index>
+        #[arg(long = "cell-dep", value_parser = parse_out_point)]
+        cell_deps: Vec<OutPoint>,
+        /// the index of the witness passed to the wrapped script, right after the inputs by default, a later index
+        /// leaves room for the inputs added to the transaction
+        #[arg(long)]
+        inner_witness_index: Option<u16>,
+        /// the witness passed to the wrapped script
+        #[arg(long, value_parser = parse_hex, default_value = "0x")]
+        inner_witness: Bytes,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Prints the error a contract exits with
+    DecodeExitCode {
+        /// the contract, the one whose range has the code by default
+        #[arg(long, value_parser = parse_contract)]
+        contract: Option<Contract>,
+        #[arg(allow_negative_numbers = true)]
+        code: i8,
+    },
+    /// Checks the linked list of the registry, or runs the checks of the contracts on a transaction, exits with an
+    /// error if anything fails
+    Verify {
+        #[command(flatten)]
+        state: State,
+        /// a JSON file with the transaction to check, the cells dump must have its inputs and cell deps
+        #[arg(long)]
+        tx: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args)]
+struct State {
+    /// the registry file written by init
+    #[arg(long)]
+    registry: PathBuf,
+    /// a JSON dump of the live cells, the cells of the other scripts are ignored
+    #[arg(long)]
+    cells: PathBuf,
+}
+
+impl State {
+    fn load(&self) -> Result<(RegistryConfig, Vec<JsonCell>), Error> {
+        Ok((read_json(&self.registry)?, read_json(&self.cells)?))
+    }
+}
+
+#[derive(clap::Args)]
+struct Output {
+    /// where to write the unsigned transaction, stdout by default
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+impl Output {
+    fn write(&self, tx: &json_types::Transaction) -> Result<(), Error> {
+        match &self.output {
+            Some(path) => write_json(path, tx),
+            None => {
+                println!("{}", serde_json::to_string_pretty(tx)?);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Error> {
+    match command {
+        Command::Init {
+            contracts,
+            first_input,
+            registry,
+            output,
+        } => {
+            let (config, tx) = registry_cli::init(&read_json(&contracts)?, first_input)?;
+            write_json(&registry, &config)?;
+            output.write(&tx)?;
+        }
+        Command::Insert { state, key, output } => {
+            let (registry, cells) = state.load()?;
+            output.write(&registry_cli::insert(&registry, &cells, key)?)?;
+        }
+        Command::SetConfig {
+            state,
+            key,
+            config,
+            output,
+        } => {
+            let (registry, cells) = state.load()?;
+            output.write(&registry_cli::set_config(&registry, &cells, key, &config)?)?;
+        }
+        Command::Lookup { state, key, since } => {
+            let (registry, cells) = state.load()?;
+            match registry_cli::lookup(&registry, &cells, key, since)? {
+                Some(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+                None => {
+                    eprintln!("the key is not covered by any node");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        }
+        Command::Unlock {
+            state,
+            key,
+            wrapped_script,
+            inputs,
+            cell_deps,
+            inner_witness_index,
+            inner_witness,
+            output,
+        } => {
+            let (registry, cells) = state.load()?;
+            let wrapped_script: json_types::Script = read_json(&wrapped_script)?;
+            let wrapped_script: Script = wrapped_script.into();
+            let key = key.unwrap_or_else(|| calc_script_hash(wrapped_script.as_slice()));
+            let tx = registry_cli::unlock(
+                &registry,
+                &cells,
+                key,
+                &wrapped_script,
+                inputs,
+                cell_deps,
+                inner_witness_index,
+                inner_witness,
+            )?;
+            output.write(&tx)?;
+        }
+        Command::DecodeExitCode { contract, code } => {
+            match contract.or_else(|| Contract::of_exit_code(code)) {
+                Some(contract) => println!("{}", decode_exit_code(contract, code)),
+                None => {
+                    eprintln!("exit code {} is not in the range of any contract", code);
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        }
+        Command::Verify { state, tx } => {
+            let (registry, cells) = state.load()?;
+            let issues: Vec<String> = match tx {
+                Some(tx) => registry_cli::verify_tx(&registry, &cells, read_json(&tx)?)?
+                    .iter()
+                    .map(|err| match err.error_info() {
+                        Some(info) => format!("{} ({})", err, info),
+                        None => err.to_string(),
+                    })
+                    .collect(),
+                None => registry_cli::verify(&registry, &cells)?
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            };
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            if !issues.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    Ok(fs::write(path, serde_json::to_string_pretty(value)?)?)
+}
+
+fn parse_key(hex: &str) -> Result<Key, String> {
+    parse_hex(hex)?
+        .as_ref()
+        .try_into()
+        .map_err(|_| "a key is 32 bytes".to_string())
+}
+
+fn parse_out_point(out_point: &str) -> Result<OutPoint, String> {
+    let (tx_hash, index) = out_point
+        .split_once(':')
+        .ok_or("an out point is <tx_hash>:<index>")?;
+    let tx_hash = parse_key(tx_hash)?;
+    let index: u32 = index.parse().map_err(|err| format!("{}", err))?;
+    Ok(OutPoint::new(tx_hash.pack(), index))
+}
+
+fn parse_contract(name: &str) -> Result<Contract, String> {
+    Contract::from_name(name).ok_or_else(|| format!("unknown contract {}", name))
+}
@@ -0,0 +1,196 @@
+//! The argv passed by lock-wrapper to the wrapped scripts.
+//!
+//! The legacy layout (version 0) is `[hex(args), hex(witness_index)...]`. Since version 1 the first
+//! argument is a single byte holding the version, which never collides with the hex encoded args of
+//! the legacy layout, version 1 is
+//! `[version, hex(args), hex(wrapper_script_hash), hex(group_inputs), hex(witness_index)...]`,
+//! where `group_inputs` are the indexes of the lock-wrapper group inputs in the transaction, each
+//! encoded as a little endian u32, and each witness index is a little endian u16.
+//!
+//! Version 2 passes the same fields as version 1 without hex encoding. The raw bytes are escaped so
+//! they never contain nul, which terminates the C strings of argv: `0x00` is encoded as
+//! `[ESCAPE, 0x01]`, `ESCAPE` as `[ESCAPE, 0x02]`, and the other bytes are kept as is.
+
+use alloc::{ffi::CString, vec::Vec};
+use core::ffi::CStr;
+
+/// The legacy layout, it has no version byte
+pub const ARGV_VERSION_LEGACY: u8 = 0;
+/// The layout passing the group context of lock-wrapper
+pub const ARGV_VERSION_1: u8 = 1;
+/// The layout of version 1 with the escaped binary encoding
+pub const ARGV_VERSION_2: u8 = 2;
+
+const ESCAPE: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgvError {
+    UnsupportedVersion,
+    InvalidEncoding,
+    InvalidLength,
+}
+
+/// The context passed by lock-wrapper to a wrapped script
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WrapperArgv {
+    pub version: u8,
+    /// the args of the wrapped script
+    pub args: Vec<u8>,
+    /// the hash of the lock-wrapper script, zero in the legacy layout
+    pub wrapper_script_hash: [u8; 32],
+    /// the indexes of the lock-wrapper group inputs in the transaction, empty in the legacy layout
+    pub group_inputs: Vec<u32>,
+    /// the indexes of the witnesses of the proofs
+    pub witness_indexes: Vec<u16>,
+}
+
+impl WrapperArgv {
+    pub fn encode(&self) -> Result<Vec<CString>, ArgvError> {
+        let encode = encoder(self.version)?;
+        let mut argv = Vec::with_capacity(self.witness_indexes.len() + 4);
+        if self.version == ARGV_VERSION_LEGACY {
+            argv.push(encode(&self.args));
+        } else {
+            argv.push(CString::new([self.version]).unwrap());
+            argv.push(encode(&self.args));
+            argv.push(encode(&self.wrapper_script_hash));
+            let group_inputs: Vec<u8> = self
+                .group_inputs
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect();
+            argv.push(encode(&group_inputs));
+        }
+        argv.extend(
+            self.witness_indexes
+                .iter()
+                .map(|index| encode(&index.to_le_bytes())),
+        );
+        Ok(argv)
+    }
+
+    pub fn decode<'a>(argv: impl IntoIterator<Item = &'a CStr>) -> Result<Self, ArgvError> {
+        let mut argv = argv.into_iter();
+        let first = argv.next().ok_or(ArgvError::InvalidLength)?;
+        let version = match first.to_bytes() {
+            [version] if !version.is_ascii_hexdigit() => *version,
+            _ => ARGV_VERSION_LEGACY,
+        };
+        let decode = decoder(version)?;
+
+        let mut wrapper_argv = if version == ARGV_VERSION_LEGACY {
+            WrapperArgv {
+                version,
+                args: decode(first)?,
+                ..Default::default()
+            }
+        } else {
+            let args = decode(argv.next().ok_or(ArgvError::InvalidLength)?)?;
+            let wrapper_script_hash = decode(argv.next().ok_or(ArgvError::InvalidLength)?)?
+                .try_into()
+                .map_err(|_| ArgvError::InvalidLength)?;
+            let group_inputs = decode(argv.next().ok_or(ArgvError::InvalidLength)?)?;
+            if group_inputs.len() % 4 != 0 {
+                return Err(ArgvError::InvalidLength);
+            }
+            WrapperArgv {
+                version,
+                args,
+                wrapper_script_hash,
+                group_inputs: group_inputs
+                    .chunks_exact(4)
+                    .map(|index| u32::from_le_bytes([index[0], index[1], index[2], index[3]]))
+                    .collect(),
+                witness_indexes: Vec::new(),
+            }
+        };
+
+        for arg in argv {
+            let index = decode(arg)?;
+            let index: [u8; 2] = index.try_into().map_err(|_| ArgvError::InvalidLength)?;
+            wrapper_argv.witness_indexes.push(u16::from_le_bytes(index));
+        }
+        if wrapper_argv.witness_indexes.is_empty() {
+            return Err(ArgvError::InvalidLength);
+        }
+        Ok(wrapper_argv)
+    }
+}
+
+// the fields are hex encoded before version 2
+fn encoder(version: u8) -> Result<fn(&[u8]) -> CString, ArgvError> {
+    match version {
+        ARGV_VERSION_LEGACY | ARGV_VERSION_1 => Ok(encode_hex),
+        ARGV_VERSION_2 => Ok(encode_binary),
+        _ => Err(ArgvError::UnsupportedVersion),
+    }
+}
+
+type Decoder = fn(&CStr) -> Result<Vec<u8>, ArgvError>;
+
+fn decoder(version: u8) -> Result<Decoder, ArgvError> {
+    match version {
+        ARGV_VERSION_LEGACY | ARGV_VERSION_1 => Ok(decode_hex),
+        ARGV_VERSION_2 => Ok(decode_binary),
+        _ => Err(ArgvError::UnsupportedVersion),
+    }
+}
+
+fn encode_hex(data: &[u8]) -> CString {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let hex: Vec<u8> = data
+        .iter()
+        .flat_map(|byte| [HEX[(byte >> 4) as usize], HEX[(byte & 0xf) as usize]])
+        .collect();
+    // hex digits never contain nul
+    CString::new(hex).unwrap()
+}
+
+fn encode_binary(data: &[u8]) -> CString {
+    let mut escaped = Vec::with_capacity(data.len());
+    for byte in data {
+        match *byte {
+            0 => escaped.extend_from_slice(&[ESCAPE, 0x01]),
+            ESCAPE => escaped.extend_from_slice(&[ESCAPE, 0x02]),
+            byte => escaped.push(byte),
+        }
+    }
+    // nul is always escaped
+    CString::new(escaped).unwrap()
+}
+
+fn decode_binary(escaped: &CStr) -> Result<Vec<u8>, ArgvError> {
+    let mut data = Vec::with_capacity(escaped.to_bytes().len());
+    let mut bytes = escaped.to_bytes().iter();
+    while let Some(byte) = bytes.next() {
+        if *byte != ESCAPE {
+            data.push(*byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(0x01) => data.push(0),
+            Some(0x02) => data.push(ESCAPE),
+            _ => return Err(ArgvError::InvalidEncoding),
+        }
+    }
+    Ok(data)
+}
+
+fn decode_hex(hex: &CStr) -> Result<Vec<u8>, ArgvError> {
+    let hex = hex.to_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return Err(ArgvError::InvalidEncoding);
+    }
+    hex.chunks_exact(2)
+        .map(|pair| Ok((decode_hex_digit(pair[0])? << 4) | decode_hex_digit(pair[1])?))
+        .collect()
+}
+
+fn decode_hex_digit(digit: u8) -> Result<u8, ArgvError> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(ArgvError::InvalidEncoding),
+    }
+}
@@ -0,0 +1,298 @@
+//! The exit codes of the contracts.
+//!
+//! Each contract exits with the codes of its own range, so a code alone tells which contract failed:
+//!
+//! | contract        | codes   |
+//! | --------------- | ------- |
+//! | global-registry | 10..=39 |
+//! | lock-wrapper    | 40..=79 |
+//! | demo-script-a   | 80..=89 |
+//! | demo-script-b   | 90..=99 |
+//!
+//! The `Error` enum of a contract starts at the first code of its range and the variants are numbered in order,
+//! the tables below list them in the same order. New variants are appended to both, `test_error_tables_match_contracts`
+//! checks the tables against the enums.
+
+use core::{fmt, ops::Range};
+
+pub const GLOBAL_REGISTRY_CODES: Range<i8> = 10..40;
+pub const LOCK_WRAPPER_CODES: Range<i8> = 40..80;
+pub const DEMO_SCRIPT_A_CODES: Range<i8> = 80..90;
+pub const DEMO_SCRIPT_B_CODES: Range<i8> = 90..100;
+
+// the errors converted from the syscall errors, every contract starts with them
+static SYS_ERRORS: [(&str, &str); 4] = [
+    (
+        "IndexOutOfBound",
+        "a syscall reads past the last cell, input or witness",
+    ),
+    (
+        "ItemMissing",
+        "a syscall reads a field the cell doesn't have",
+    ),
+    ("LengthNotEnough", "a syscall reads into a too small buffer"),
+    (
+        "Encoding",
+        "a molecule structure or the argv can't be decoded",
+    ),
+];
+
+static GLOBAL_REGISTRY_ERRORS: [(&str, &str); 11] = [
+    (
+        "InvalidInitHash",
+        "the type args of the new registry are not GlobalRegistryArgs derived from its first input",
+    ),
+    (
+        "InvalidDataLength",
+        "the data of a registry cell is shorter than the end key",
+    ),
+    (
+        "InvalidArgsLength",
+        "the lock args of a registry cell are not LockWrapperArgs",
+    ),
+    (
+        "InvalidLinkedList",
+        "the registry outputs don't re-partition the ranges of the registry inputs",
+    ),
+    (
+        "InvalidInitOutputCount",
+        "more than one registry cell is created at initialization",
+    ),
+    (
+        "InvalidSentinelStart",
+        "the sentinel doesn't start at the min key",
+    ),
+    (
+        "InvalidSentinelEnd",
+        "the sentinel doesn't end at the max key",
+    ),
+    (
+        "TooManyNodes",
+        "more than 64 registry cells are in the inputs or the outputs",
+    ),
+    (
+        "InvalidSentinelLock",
+        "the sentinel is not locked by the lock-wrapper in the type args of this registry",
+    ),
+    (
+        "InvalidInsertedConfig",
+        "a node inserted by a split overrides the wrapped script of its start key",
+    ),
+    (
+        "InvalidNodeLock",
+        "a registry output is not locked by the lock-wrapper in the type args of this registry",
+    ),
+];
+
+static LOCK_WRAPPER_ERRORS: [(&str, &str); 24] = [
+    (
+        "InvalidCellDepTypeScript",
+        "no registry cell is in the cell deps",
+    ),
+    (
+        "InvalidDataLength",
+        "the data of the registry node is shorter than the end key",
+    ),
+    ("InvalidArgsLength", "the lock args are not LockWrapperArgs"),
+    (
+        "InvalidCellDepRef",
+        "no registry cell in the cell deps covers the key",
+    ),
+    (
+        "InvalidInputCount",
+        "the lock group spends other cells with its registry cell, or more than one registry cell",
+    ),
+    (
+        "InvalidOutputLockScript",
+        "the updated registry cell has several successors locked by the same script, or a removed one is not merged",
+    ),
+    ("InvalidWitnessFormat", "the witness is not a WitnessArgs"),
+    (
+        "InvalidWrappedScriptHash",
+        "the proof is not of the wrapped script selected by the registry",
+    ),
+    (
+        "InvalidNodeConfig",
+        "the config payload is neither a hash nor a NodeConfig",
+    ),
+    (
+        "WrappedScriptFailed",
+        "a spawned wrapped script exits with a non-zero code",
+    ),
+    (
+        "InvalidWrappedScriptHashType",
+        "the wrapped script has an unknown hash type",
+    ),
+    (
+        "InvalidWrapperWitness",
+        "the wrapper witness is malformed or of an unexpected kind",
+    ),
+    (
+        "MissingWrapperWitness",
+        "the first input of the lock group has no wrapper witness",
+    ),
+    (
+        "InvalidConfigChange",
+        "the owners of the node are changed without an activated pending config",
+    ),
+    (
+        "MissingGuardianSet",
+        "the node has no guardians to recover it",
+    ),
+    (
+        "GuardianDelayNotReached",
+        "the recovery is not requested the guardian delay before",
+    ),
+    (
+        "InvalidMultiProof",
+        "a proof is not of a listed script or proves a script twice",
+    ),
+    (
+        "ThresholdNotReached",
+        "fewer scripts than the threshold are proved",
+    ),
+    (
+        "SpawnRequired",
+        "several wrapped scripts must run, which requires spawn (VM version 2)",
+    ),
+    (
+        "UnsupportedArgvVersion",
+        "the proof selects an unknown argv version",
+    ),
+    (
+        "UnsupportedVmVersion",
+        "lock-wrapper runs on VM version 0 (hash type data), which can't run the wrapped scripts",
+    ),
+    (
+        "InvalidRequestSince",
+        "a new request has a non-zero since, or a carried one is not anchored to the block of the input",
+    ),
+    (
+        "InvalidRecovery",
+        "the guardians change the config when requesting the recovery, or keep the request when executing it",
+    ),
+    (
+        "MixedArgvVersions",
+        "the proofs of the group inputs ask for different argv versions",
+    ),
+];
+
+static DEMO_SCRIPT_ERRORS: [(&str, &str); 2] = [
+    (
+        "WrongArgv",
+        "the argv passed by lock-wrapper can't be decoded",
+    ),
+    (
+        "WrongWitness",
+        "the inner witness doesn't match the script args",
+    ),
+];
+
+/// A contract of the registry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Contract {
+    GlobalRegistry,
+    LockWrapper,
+    DemoScriptA,
+    DemoScriptB,
+}
+
+impl Contract {
+    pub const ALL: [Contract; 4] = [
+        Contract::GlobalRegistry,
+        Contract::LockWrapper,
+        Contract::DemoScriptA,
+        Contract::DemoScriptB,
+    ];
+
+    /// The name of the contract binary
+    pub fn name(&self) -> &'static str {
+        match self {
+            Contract::GlobalRegistry => "global-registry",
+            Contract::LockWrapper => "lock-wrapper",
+            Contract::DemoScriptA => "demo-script-a",
+            Contract::DemoScriptB => "demo-script-b",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Contract> {
+        Self::ALL
+            .into_iter()
+            .find(|contract| contract.name() == name)
+    }
+
+    /// The range of the exit codes of the contract
+    pub fn codes(&self) -> Range<i8> {
+        match self {
+            Contract::GlobalRegistry => GLOBAL_REGISTRY_CODES,
+            Contract::LockWrapper => LOCK_WRAPPER_CODES,
+            Contract::DemoScriptA => DEMO_SCRIPT_A_CODES,
+            Contract::DemoScriptB => DEMO_SCRIPT_B_CODES,
+        }
+    }
+
+    /// The contract whose range has the exit code
+    pub fn of_exit_code(code: i8) -> Option<Contract> {
+        Self::ALL
+            .into_iter()
+            .find(|contract| contract.codes().contains(&code))
+    }
+
+    /// The error of the contract with the variant name
+    pub fn find_error(&self, name: &str) -> Option<ErrorInfo> {
+        self.errors()
+            .position(|(error_name, _)| error_name == name)
+            .map(|index| decode_exit_code(*self, self.codes().start + index as i8))
+    }
+
+    // the (name, description) of the errors in the order of the variants
+    fn errors(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        let errors: &[(&str, &str)] = match self {
+            Contract::GlobalRegistry => &GLOBAL_REGISTRY_ERRORS,
+            Contract::LockWrapper => &LOCK_WRAPPER_ERRORS,
+            Contract::DemoScriptA | Contract::DemoScriptB => &DEMO_SCRIPT_ERRORS,
+        };
+        SYS_ERRORS.iter().chain(errors).copied()
+    }
+}
+
+impl fmt::Display for Contract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An exit code decoded by `decode_exit_code`, the name is the variant of the contract `Error` enum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub contract: Contract,
+    pub code: i8,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} error {} {}: {}",
+            self.contract, self.code, self.name, self.description
+        )
+    }
+}
+
+/// Decodes the exit code of a contract, the codes outside the errors of the contract are reported as unknown
+pub fn decode_exit_code(contract: Contract, code: i8) -> ErrorInfo {
+    let error = code
+        .checked_sub(contract.codes().start)
+        .filter(|_| contract.codes().contains(&code))
+        .and_then(|index| contract.errors().nth(index as usize));
+    let (name, description) =
+        error.unwrap_or(("Unknown", "the code is not an error of the contract"));
+    ErrorInfo {
+        contract,
+        code,
+        name,
+        description,
+    }
+}
@@ -0,0 +1,35 @@
+use blake2b_rs::{Blake2b, Blake2bBuilder};
+
+pub const HASH_SIZE: usize = 32;
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+/// Creates a blake2b hasher with the same parameters as `ckb-hash`
+pub fn new_blake2b() -> Blake2b {
+    Blake2bBuilder::new(HASH_SIZE)
+        .personal(CKB_HASH_PERSONALIZATION)
+        .build()
+}
+
+pub fn blake2b_256(data: &[u8]) -> [u8; HASH_SIZE] {
+    let mut hash = [0; HASH_SIZE];
+    let mut blake2b = new_blake2b();
+    blake2b.update(data);
+    blake2b.finalize(&mut hash);
+    hash
+}
+
+/// Calculates the hash of a molecule encoded `Script`
+pub fn calc_script_hash(script: &[u8]) -> [u8; HASH_SIZE] {
+    blake2b_256(script)
+}
+
+/// Calculates the type args of a new registry, which is the hash of the first input (molecule encoded `CellInput`)
+/// and the index of the first output with the global-registry type script
+pub fn calc_init_hash(first_input: &[u8], first_output_index: u64) -> [u8; HASH_SIZE] {
+    let mut hash = [0; HASH_SIZE];
+    let mut blake2b = new_blake2b();
+    blake2b.update(first_input);
+    blake2b.update(&first_output_index.to_le_bytes());
+    blake2b.finalize(&mut hash);
+    hash
+}
@@ -0,0 +1,38 @@
+//! Definitions shared by the contracts and the off-chain code of the global registry.
+//!
+//! The crate is `no_std` so it can be linked into the riscv contracts, enable the `std` feature
+//! when using it on the host.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod argv;
+pub mod error;
+pub mod hash;
+pub mod linked_list;
+pub mod node;
+pub mod since;
+
+pub use registry_schema as schema;
+
+/// Implements `From<SysError>` for a contract error type which defines the
+/// `IndexOutOfBound`, `ItemMissing`, `LengthNotEnough` and `Encoding` variants, the errors of the spawn
+/// syscalls are not expected by the callers of the macro.
+#[macro_export]
+macro_rules! impl_from_sys_error {
+    ($error:ty) => {
+        impl From<::ckb_std::error::SysError> for $error {
+            fn from(err: ::ckb_std::error::SysError) -> Self {
+                use ::ckb_std::error::SysError::*;
+                match err {
+                    IndexOutOfBound => Self::IndexOutOfBound,
+                    ItemMissing => Self::ItemMissing,
+                    LengthNotEnough(_) => Self::LengthNotEnough,
+                    Encoding => Self::Encoding,
+                    err => panic!("unexpected sys error {:?}", err),
+                }
+            }
+        }
+    };
+}
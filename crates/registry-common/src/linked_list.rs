@@ -0,0 +1,124 @@
+//! The linked list check of global-registry, shared with the pre-flight verifier so both walk the ranges the same
+//! way.
+
+use alloc::vec::Vec;
+
+use crate::node::{Key, Range};
+
+/// The max number of registry cells in the inputs or outputs of a transaction, which keeps the cycles of sorting
+/// bounded
+pub const MAX_NODES_PER_TX: usize = 64;
+
+/// The first break of the linked list found by `check_linked_list`, the indexes are the ones paired with the ranges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkedListError {
+    /// no output continues the linked list at the key
+    MissingOutput {
+        start: Key,
+    },
+    /// the output doesn't start where the previous one ends
+    UnexpectedOutputStart {
+        output: usize,
+        start: Key,
+        expected: Key,
+    },
+    EmptyOutput {
+        output: usize,
+        start: Key,
+    },
+    /// the output ends after the last input
+    OutputBeyondInputs {
+        output: usize,
+        end: Key,
+    },
+    /// the input merged by an output doesn't start where the previous input ends
+    NonConsecutiveInputs {
+        input: usize,
+        start: Key,
+        expected: Key,
+    },
+    /// the output is not covered by the inputs
+    ExtraOutput {
+        output: usize,
+        start: Key,
+    },
+}
+
+/// Sorts the ranges paired with the indexes of their cells by start key. The sort is unstable, ranges of the same
+/// start break the linked list whatever their order
+pub fn sort_ranges(ranges: &mut [(usize, Range)]) {
+    ranges.sort_unstable_by_key(|(_, range)| range.start);
+}
+
+/// Checks that the outputs re-partition the ranges covered by the inputs, both sorted by `sort_ranges`: an input
+/// range [a, c) can be split into [a, b), [b, c), and consecutive input ranges [a, b), [b, c) can be merged into
+/// [a, c). Returns the outputs inserted by a split with their start keys
+pub fn check_linked_list(
+    inputs: &[(usize, Range)],
+    outputs: &[(usize, Range)],
+) -> Result<Vec<(usize, Key)>, LinkedListError> {
+    let mut i = 0;
+    let mut o = 0;
+    let mut inserted = Vec::new();
+    while let Some((_, input)) = inputs.get(i) {
+        i += 1;
+        let mut cursor = input.start;
+        let mut input_end = input.end;
+        loop {
+            let (output_index, output) = outputs
+                .get(o)
+                .ok_or(LinkedListError::MissingOutput { start: cursor })?;
+            if output.start != cursor {
+                return Err(LinkedListError::UnexpectedOutputStart {
+                    output: *output_index,
+                    start: output.start,
+                    expected: cursor,
+                });
+            }
+            if output.is_empty() {
+                return Err(LinkedListError::EmptyOutput {
+                    output: *output_index,
+                    start: output.start,
+                });
+            }
+            o += 1;
+            if cursor != input.start {
+                inserted.push((*output_index, output.start));
+            }
+
+            // the output covers more than the current input range, merge the following input ranges which must be
+            // consecutive
+            while output.end > input_end {
+                let (input_index, next) =
+                    inputs.get(i).ok_or(LinkedListError::OutputBeyondInputs {
+                        output: *output_index,
+                        end: output.end,
+                    })?;
+                if next.start != input_end {
+                    return Err(LinkedListError::NonConsecutiveInputs {
+                        input: *input_index,
+                        start: next.start,
+                        expected: input_end,
+                    });
+                }
+                i += 1;
+                input_end = next.end;
+            }
+
+            if output.end != input_end {
+                cursor = output.end;
+            } else {
+                break;
+            }
+        }
+    }
+
+    // check if all the outputs are visited
+    match outputs.get(o) {
+        Some((output, range)) => Err(LinkedListError::ExtraOutput {
+            output: *output,
+            start: range.start,
+        }),
+        None => Ok(inserted),
+    }
+}
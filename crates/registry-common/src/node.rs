@@ -0,0 +1,319 @@
+use core::cmp::Ordering;
+
+use registry_schema::{
+    molecule::prelude::*, GlobalRegistryArgsReader, LockWrapperArgsReader, NodeConfig,
+    NodeConfigReader, PendingConfigOpt, RecoveryRequestOpt, ScriptReader,
+};
+
+use crate::since::{
+    is_absolute_since_reached, is_relative_since_reached, METRIC_EPOCH, RELATIVE_FLAG,
+};
+
+pub type Key = [u8; 32];
+pub const KEY_SIZE: usize = 32;
+
+/// The first key of the sentinel node created at initialization
+pub const KEY_MIN: Key = [0u8; 32];
+/// The end of the sentinel node created at initialization, the sentinel covers the whole key space
+pub const KEY_MAX: Key = [0xffu8; 32];
+/// The config value of a node without override, the wrapped script hash in the lock args applies to the start key
+pub const NO_OVERRIDE: Key = [0u8; 32];
+/// The minimum delay between proposing a pending config and its activation, a relative since of 6 epochs (about a
+/// day), the current owners can cancel the pending config meanwhile
+pub const PENDING_DELAY: u64 = RELATIVE_FLAG | METRIC_EPOCH | 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    InvalidArgs,
+    InvalidData,
+    InvalidConfig,
+}
+
+/// The key range [start, end) covered by a registry node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub start: Key,
+    pub end: Key,
+}
+
+/// The result of looking up a key in a registry node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    /// the key is the start of the node, the config value of the node applies to it
+    Start,
+    /// the key is inside the node, it has no config value
+    Inside,
+    /// the key is not covered by the node
+    Outside,
+}
+
+impl Range {
+    pub fn new(start: Key, end: Key) -> Self {
+        Range { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    pub fn lookup(&self, key: &Key) -> Lookup {
+        match self.start.cmp(key) {
+            Ordering::Equal => Lookup::Start,
+            Ordering::Less if key < &self.end => Lookup::Inside,
+            _ => Lookup::Outside,
+        }
+    }
+}
+
+/// The change of the since of a request from the input config to the output config of a node. A request is a delayed
+/// change of the node, its since is zero when it is made and anchored by the first transaction spending the cell
+/// holding it: that cell was created by the request, so the output records the absolute since the delay after the
+/// block of the input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestSince {
+    /// the request is removed, made with a zero since, or carried with its anchored since
+    Valid,
+    /// the unanchored request is carried with the since, which must be the absolute since the delay after the block
+    /// of the input
+    Anchored(u64),
+    Invalid,
+}
+
+/// Checks the since of a request of `content` in the input and the output config, a request replaced by another
+/// content is a new one
+pub fn check_request_since<T: PartialEq>(
+    input: Option<(T, u64)>,
+    output: Option<(T, u64)>,
+) -> RequestSince {
+    match (input, output) {
+        (_, None) => RequestSince::Valid,
+        (Some((input, input_since)), Some((output, since))) if input == output => {
+            match (input_since, since) {
+                (0, 0) => RequestSince::Invalid,
+                (0, since) => RequestSince::Anchored(since),
+                (input_since, since) if input_since == since => RequestSince::Valid,
+                _ => RequestSince::Invalid,
+            }
+        }
+        (_, Some((_, 0))) => RequestSince::Valid,
+        _ => RequestSince::Invalid,
+    }
+}
+
+/// Returns true if the configs only differ in the since of their requests, which is checked by `check_request_since`
+pub fn is_config_carried(input: &NodeConfig, output: &NodeConfig) -> bool {
+    without_request_since(input).as_slice() == without_request_since(output).as_slice()
+}
+
+/// Returns true if the output config only adds a recovery request to the input config, its since is checked by
+/// `check_request_since`
+pub fn is_recovery_requested(input: &NodeConfig, output: &NodeConfig) -> bool {
+    input.recovery().is_none()
+        && output.recovery().is_some()
+        && output
+            .clone()
+            .as_builder()
+            .recovery(RecoveryRequestOpt::default())
+            .build()
+            .as_slice()
+            == input.as_slice()
+}
+
+fn without_request_since(config: &NodeConfig) -> NodeConfig {
+    let pending = config
+        .pending()
+        .to_opt()
+        .map(|pending| pending.as_builder().since(0u64.into()).build());
+    let recovery = config
+        .recovery()
+        .to_opt()
+        .map(|recovery| recovery.as_builder().since(0u64.into()).build());
+    config
+        .clone()
+        .as_builder()
+        .pending(PendingConfigOpt::new_builder().set(pending).build())
+        .recovery(RecoveryRequestOpt::new_builder().set(recovery).build())
+        .build()
+}
+
+// a request anchored at `since` is activated once any input since reaches it, an unanchored one once the registry
+// input holding it is spent `delay` after its creation, its since is only known when the node is updated
+fn is_request_activated(
+    since: u64,
+    delay: u64,
+    inputs_since: &[u64],
+    registry_input_since: Option<u64>,
+) -> bool {
+    if since == 0 {
+        registry_input_since
+            .is_some_and(|input_since| is_relative_since_reached(input_since, delay))
+    } else {
+        inputs_since
+            .iter()
+            .any(|input_since| is_absolute_since_reached(*input_since, since))
+    }
+}
+
+/// A registry node decoded from the lock args and the data of a registry cell
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub range: Range,
+    pub config: NodeConfig,
+}
+
+impl Node {
+    pub fn from_slices(lock_args: &[u8], data: &[u8]) -> Result<Self, LayoutError> {
+        let range = decode_range(lock_args, data)?;
+        let config = decode_config(&data[KEY_SIZE..])?;
+        Ok(Node { range, config })
+    }
+
+    /// The wrapped script hash which applies to the start key, the start key itself if the node has no override
+    pub fn wrapped_script_hash(&self) -> Key {
+        self.resolve(self.config.wrapped_script_hash().into())
+    }
+
+    /// The wrapped script hash and the since of the pending config
+    pub fn pending_request(&self) -> Option<(Key, u64)> {
+        self.config.pending().to_opt().map(|pending| {
+            (
+                pending.wrapped_script_hash().into(),
+                pending.since().as_reader().into(),
+            )
+        })
+    }
+
+    /// The guardian delay and the since of the recovery request, a request without guardians can't be executed
+    pub fn recovery_request(&self) -> Option<(u64, u64)> {
+        let guardians = self.config.guardians().to_opt()?;
+        self.config.recovery().to_opt().map(|recovery| {
+            (
+                guardians.delay().as_reader().into(),
+                recovery.since().as_reader().into(),
+            )
+        })
+    }
+
+    /// Returns true if the node has a request whose since is not anchored yet
+    pub fn has_unanchored_request(&self) -> bool {
+        matches!(self.pending_request(), Some((_, 0)))
+            || matches!(self.recovery_request(), Some((_, 0)))
+    }
+
+    /// Returns true if the node has a recovery request which is activated, like `is_pending_activated` with the
+    /// guardian delay
+    pub fn is_recovery_activated(
+        &self,
+        inputs_since: &[u64],
+        registry_input_since: Option<u64>,
+    ) -> bool {
+        match self.recovery_request() {
+            Some((delay, since)) => {
+                is_request_activated(since, delay, inputs_since, registry_input_since)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the node has a pending config which is activated in a transaction with the given input since
+    /// values, the transaction can only be committed after all of them, so any of them reaching the anchored since is
+    /// enough. An unanchored pending config is only activated when the node is updated, `registry_input_since` is the
+    /// since of the registry input then
+    pub fn is_pending_activated(
+        &self,
+        inputs_since: &[u64],
+        registry_input_since: Option<u64>,
+    ) -> bool {
+        match self.pending_request() {
+            Some((_, since)) => {
+                is_request_activated(since, PENDING_DELAY, inputs_since, registry_input_since)
+            }
+            None => false,
+        }
+    }
+
+    /// The wrapped script hash which applies to the start key in a transaction with the given input since values,
+    /// it is the pending one once activated
+    pub fn active_wrapped_script_hash(
+        &self,
+        inputs_since: &[u64],
+        registry_input_since: Option<u64>,
+    ) -> Key {
+        match self.pending_request() {
+            Some((pending_hash, _))
+                if self.is_pending_activated(inputs_since, registry_input_since) =>
+            {
+                self.resolve(pending_hash)
+            }
+            _ => self.wrapped_script_hash(),
+        }
+    }
+
+    fn resolve(&self, hash: Key) -> Key {
+        if hash == NO_OVERRIDE {
+            self.range.start
+        } else {
+            hash
+        }
+    }
+}
+
+/// Decodes the range of a registry cell, only the linked list portion of the data is checked
+pub fn decode_range(lock_args: &[u8], data: &[u8]) -> Result<Range, LayoutError> {
+    let (_registry_hash, start) = decode_lock_args(lock_args)?;
+    if data.len() < KEY_SIZE {
+        return Err(LayoutError::InvalidData);
+    }
+    let mut end = [0u8; KEY_SIZE];
+    end.copy_from_slice(&data[..KEY_SIZE]);
+    Ok(Range::new(start, end))
+}
+
+/// Decodes the config payload following the end key, a 32 bytes payload is the wrapped script hash,
+/// otherwise it must be a molecule encoded `NodeConfig`
+pub fn decode_config(payload: &[u8]) -> Result<NodeConfig, LayoutError> {
+    if payload.len() == KEY_SIZE {
+        let mut wrapped_script_hash = [0u8; KEY_SIZE];
+        wrapped_script_hash.copy_from_slice(payload);
+        Ok(NodeConfig::new_builder()
+            .wrapped_script_hash(wrapped_script_hash.into())
+            .build())
+    } else {
+        NodeConfigReader::verify(payload, true).map_err(|_| LayoutError::InvalidConfig)?;
+        Ok(NodeConfig::new_unchecked(payload.to_vec().into()))
+    }
+}
+
+/// Decodes the type args of global-registry into (init_hash, lock_code_hash, lock_hash_type)
+pub fn decode_registry_args(type_args: &[u8]) -> Result<(Key, Key, u8), LayoutError> {
+    let args =
+        GlobalRegistryArgsReader::from_slice(type_args).map_err(|_| LayoutError::InvalidArgs)?;
+    Ok((
+        args.init_hash().into(),
+        args.lock_code_hash().into(),
+        args.lock_hash_type().into(),
+    ))
+}
+
+/// Decodes the lock args of lock-wrapper into (registry_hash, start)
+pub fn decode_lock_args(lock_args: &[u8]) -> Result<(Key, Key), LayoutError> {
+    let args =
+        LockWrapperArgsReader::from_slice(lock_args).map_err(|_| LayoutError::InvalidArgs)?;
+    Ok((args.registry_hash().into(), args.start().into()))
+}
+
+/// Checks if the lock script of a registry cell is the lock-wrapper in the type args of the registry, with lock args
+/// naming the registry by the hash of its type script. lock-wrapper skips the registry cells locked otherwise
+pub fn is_registry_lock(lock_script: &[u8], registry_args: &[u8], registry_hash: &Key) -> bool {
+    let (lock, (_, lock_code_hash, lock_hash_type)) = match (
+        ScriptReader::from_slice(lock_script),
+        decode_registry_args(registry_args),
+    ) {
+        (Ok(lock), Ok(args)) => (lock, args),
+        _ => return false,
+    };
+    let hash_type: u8 = lock.hash_type().into();
+    lock.code_hash().as_slice() == lock_code_hash
+        && hash_type == lock_hash_type
+        && matches!(decode_lock_args(lock.args().raw_data()), Ok((hash, _)) if hash == *registry_hash)
+}
@@ -0,0 +1,85 @@
+//! Comparison of since values, see the since field of a transaction input in RFC 0017.
+
+pub(crate) const RELATIVE_FLAG: u64 = 1 << 63;
+const METRIC_MASK: u64 = 0b11 << 61;
+const METRIC_BLOCK_NUMBER: u64 = 0;
+pub(crate) const METRIC_EPOCH: u64 = 0b01 << 61;
+const METRIC_TIMESTAMP: u64 = 0b10 << 61;
+const FLAGS_MASK: u64 = 0xff << 56;
+const VALUE_MASK: u64 = !FLAGS_MASK;
+
+/// Returns true if a transaction with an input of `since` can only be committed at or after the
+/// absolute since `target`, both must be absolute and use the same metric
+pub fn is_absolute_since_reached(since: u64, target: u64) -> bool {
+    since & RELATIVE_FLAG == 0 && is_since_reached(since, target)
+}
+
+/// Returns true if an input of `since` can only be committed at least the relative since `target`
+/// after the creation of its cell, both must be relative and use the same metric
+pub fn is_relative_since_reached(since: u64, target: u64) -> bool {
+    since & RELATIVE_FLAG != 0 && is_since_reached(since, target)
+}
+
+/// The absolute since the relative since `delay` after a block of `number`, `epoch` and `timestamp` in
+/// milliseconds, in the metric of `delay`. The fraction of an epoch delay is rounded up to the length of
+/// the epoch of the block. None if `delay` is not relative or the result doesn't fit in a since value
+pub fn absolute_since_after(delay: u64, number: u64, epoch: u64, timestamp: u64) -> Option<u64> {
+    if delay & RELATIVE_FLAG == 0 || delay & FLAGS_MASK & !(RELATIVE_FLAG | METRIC_MASK) != 0 {
+        return None;
+    }
+    let value = delay & VALUE_MASK;
+    let metric = delay & METRIC_MASK;
+    let target = match metric {
+        METRIC_BLOCK_NUMBER => number.checked_add(value)?,
+        METRIC_EPOCH => epoch_after(epoch, value)?,
+        METRIC_TIMESTAMP => (timestamp / 1000).checked_add(value)?,
+        _ => return None,
+    };
+    if target & FLAGS_MASK != 0 {
+        return None;
+    }
+    Some(metric | target)
+}
+
+fn is_since_reached(since: u64, target: u64) -> bool {
+    if since & FLAGS_MASK != target & FLAGS_MASK {
+        return false;
+    }
+    if since & METRIC_MASK == METRIC_EPOCH {
+        is_epoch_reached(since & VALUE_MASK, target & VALUE_MASK)
+    } else {
+        since & VALUE_MASK >= target & VALUE_MASK
+    }
+}
+
+// the epoch of a since value is number (24 bits), index (16 bits) and length (16 bits), it is
+// compared as number + index / length, a zero length means the start of the epoch
+fn epoch_fraction(value: u64) -> (u64, u64, u64) {
+    let number = value & 0xff_ffff;
+    let index = (value >> 24) & 0xffff;
+    let length = (value >> 40) & 0xffff;
+    if length == 0 {
+        (number, 0, 1)
+    } else {
+        (number, index, length)
+    }
+}
+
+fn is_epoch_reached(since: u64, target: u64) -> bool {
+    let (number, index, length) = epoch_fraction(since);
+    let (target_number, target_index, target_length) = epoch_fraction(target);
+    number > target_number
+        || (number == target_number && index * target_length >= target_index * length)
+}
+
+// the epoch `delay` after `epoch`, in fractions of the length of `epoch`
+fn epoch_after(epoch: u64, delay: u64) -> Option<u64> {
+    let (number, index, length) = epoch_fraction(epoch);
+    let (delay_number, delay_index, delay_length) = epoch_fraction(delay);
+    let index = index + (delay_index * length).div_ceil(delay_length);
+    let number = number + delay_number + index / length;
+    if number > 0xff_ffff {
+        return None;
+    }
+    Some(number | (index % length) << 24 | length << 40)
+}
@@ -0,0 +1,16 @@
+use molecule_codegen::{Compiler, Language};
+use std::{env, path::PathBuf};
+
+const SCHEMA: &str = "../../schemas/registry.mol";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SCHEMA);
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    Compiler::new()
+        .input_schema_file(SCHEMA)
+        .generate_code(Language::Rust)
+        .output_dir(out_dir)
+        .run()
+        .unwrap();
+}
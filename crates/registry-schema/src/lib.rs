@@ -0,0 +1,61 @@
+//! Molecule types of the registry cells, generated from `schemas/registry.mol`.
+
+#![no_std]
+
+extern crate alloc;
+
+pub use molecule;
+
+#[allow(clippy::all, dead_code)]
+mod registry {
+    include!(concat!(env!("OUT_DIR"), "/registry.rs"));
+}
+
+pub use registry::*;
+
+use alloc::vec::Vec;
+use molecule::prelude::*;
+
+impl<'r> From<Byte32Reader<'r>> for [u8; 32] {
+    fn from(reader: Byte32Reader<'r>) -> Self {
+        let mut value = [0u8; 32];
+        value.copy_from_slice(reader.raw_data());
+        value
+    }
+}
+
+impl From<u16> for Uint16 {
+    fn from(value: u16) -> Self {
+        Uint16::new_unchecked(value.to_le_bytes().to_vec().into())
+    }
+}
+
+impl<'r> From<Uint16Reader<'r>> for u16 {
+    fn from(reader: Uint16Reader<'r>) -> Self {
+        let raw = reader.raw_data();
+        u16::from_le_bytes([raw[0], raw[1]])
+    }
+}
+
+impl From<u64> for Uint64 {
+    fn from(value: u64) -> Self {
+        Uint64::new_unchecked(value.to_le_bytes().to_vec().into())
+    }
+}
+
+impl<'r> From<Uint64Reader<'r>> for u64 {
+    fn from(reader: Uint64Reader<'r>) -> Self {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(reader.raw_data());
+        u64::from_le_bytes(raw)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(value: &[u8]) -> Self {
+        let mut data = Vec::with_capacity(molecule::NUMBER_SIZE + value.len());
+        data.extend_from_slice(&(value.len() as molecule::Number).to_le_bytes());
+        data.extend_from_slice(value);
+        Bytes::new_unchecked(data.into())
+    }
+}
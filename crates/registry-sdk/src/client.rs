@@ -0,0 +1,385 @@
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, HeaderView, ScriptHashType, TransactionBuilder, TransactionView},
+    packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint, Script},
+    prelude::*,
+};
+use registry_common::{
+    hash::{calc_init_hash, calc_script_hash},
+    node::{
+        is_registry_lock, Key, Lookup, Node, KEY_MAX, KEY_MIN, KEY_SIZE, NO_OVERRIDE, PENDING_DELAY,
+    },
+    schema,
+    since::absolute_since_after,
+};
+
+use crate::{
+    error::Error,
+    witness::{wrapped_proof, wrapper_witness_args},
+};
+
+/// A deployed contract, referenced by its code hash and hash type, and loaded from its cell dep
+#[derive(Clone, Debug)]
+pub struct ScriptCode {
+    pub code_hash: Byte32,
+    pub hash_type: ScriptHashType,
+    pub cell_dep: CellDep,
+}
+
+impl ScriptCode {
+    pub fn new(code_hash: Byte32, hash_type: ScriptHashType, cell_dep: CellDep) -> Self {
+        ScriptCode {
+            code_hash,
+            hash_type,
+            cell_dep,
+        }
+    }
+
+    pub fn script(&self, args: Bytes) -> Script {
+        Script::new_builder()
+            .code_hash(self.code_hash.clone())
+            .hash_type(self.hash_type.into())
+            .args(args.pack())
+            .build()
+    }
+}
+
+/// A live registry cell
+#[derive(Clone, Debug)]
+pub struct RegistryCell {
+    pub out_point: OutPoint,
+    pub output: CellOutput,
+    pub data: Bytes,
+}
+
+impl RegistryCell {
+    pub fn node(&self) -> Result<Node, Error> {
+        Ok(Node::from_slices(
+            &self.output.lock().args().raw_data(),
+            &self.data,
+        )?)
+    }
+
+    pub fn input(&self) -> CellInput {
+        CellInput::new_builder()
+            .previous_output(self.out_point.clone())
+            .build()
+    }
+
+    pub fn cell_dep(&self) -> CellDep {
+        CellDep::new_builder()
+            .out_point(self.out_point.clone())
+            .build()
+    }
+}
+
+/// Builds the transactions of a registry, the known live registry cells are tracked by `add_cell` and `apply_tx`
+#[derive(Clone, Debug)]
+pub struct RegistryClient {
+    global_registry: ScriptCode,
+    lock_wrapper: ScriptCode,
+    registry_type_script: Script,
+    cells: Vec<RegistryCell>,
+}
+
+impl RegistryClient {
+    /// A client of the registry created with `init_hash`, the type args are the `GlobalRegistryArgs` of the init
+    /// hash and the lock-wrapper code
+    pub fn new(global_registry: ScriptCode, lock_wrapper: ScriptCode, init_hash: [u8; 32]) -> Self {
+        let args = schema::GlobalRegistryArgs::new_builder()
+            .init_hash(init_hash.into())
+            .lock_code_hash(schema::Byte32::new_unchecked(
+                lock_wrapper.code_hash.as_bytes(),
+            ))
+            .lock_hash_type(lock_wrapper.hash_type.into())
+            .build();
+        let registry_type_script = global_registry.script(args.as_bytes());
+        RegistryClient {
+            global_registry,
+            lock_wrapper,
+            registry_type_script,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Creates a registry spending `first_input`, the sentinel node covering the whole key space is the first
+    /// output, so the type args are derived the same way as `validate_init_hash`
+    pub fn init(
+        global_registry: ScriptCode,
+        lock_wrapper: ScriptCode,
+        first_input: CellInput,
+    ) -> Result<(Self, TransactionView), Error> {
+        let init_hash = calc_init_hash(first_input.as_slice(), 0);
+        let client = Self::new(global_registry, lock_wrapper, init_hash);
+        let (output, data) = client.registry_cell(KEY_MIN, KEY_MAX, &NO_OVERRIDE)?;
+        let tx = TransactionBuilder::default()
+            .cell_dep(client.global_registry.cell_dep.clone())
+            .input(first_input)
+            .output(output)
+            .output_data(data.pack())
+            .build();
+        Ok((client, tx))
+    }
+
+    pub fn lock_wrapper(&self) -> &ScriptCode {
+        &self.lock_wrapper
+    }
+
+    pub fn registry_type_script(&self) -> &Script {
+        &self.registry_type_script
+    }
+
+    /// The hash of the registry type script, which is the `registry_hash` in the lock args of the registry cells
+    pub fn registry_hash(&self) -> [u8; 32] {
+        calc_script_hash(self.registry_type_script.as_slice())
+    }
+
+    /// The lock-wrapper script of the key
+    pub fn lock_script(&self, start: Key) -> Script {
+        let args = schema::LockWrapperArgs::new_builder()
+            .registry_hash(self.registry_hash().into())
+            .start(start.into())
+            .build();
+        self.lock_wrapper.script(args.as_bytes())
+    }
+
+    /// The output and the data of a registry cell covering [start, end) with the config payload, which is either
+    /// a wrapped script hash or a molecule encoded `NodeConfig`, the capacity is the occupied capacity
+    pub fn registry_cell(
+        &self,
+        start: Key,
+        end: Key,
+        config: &[u8],
+    ) -> Result<(CellOutput, Bytes), Error> {
+        let data = Bytes::from([end.as_slice(), config].concat());
+        let output = CellOutput::new_builder()
+            .lock(self.lock_script(start))
+            .type_(Some(self.registry_type_script.clone()).pack())
+            .build_exact_capacity(Capacity::bytes(data.len())?)?;
+        Ok((output, data))
+    }
+
+    pub fn cells(&self) -> &[RegistryCell] {
+        &self.cells
+    }
+
+    pub fn add_cell(&mut self, cell: RegistryCell) {
+        self.cells.push(cell);
+    }
+
+    /// Tracks a committed transaction, the spent registry cells are removed and the created ones are added
+    pub fn apply_tx(&mut self, tx: &TransactionView) {
+        let spent: Vec<OutPoint> = tx.input_pts_iter().collect();
+        self.cells.retain(|cell| !spent.contains(&cell.out_point));
+        for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+            if output.type_().to_opt().as_ref() == Some(&self.registry_type_script) {
+                self.cells.push(RegistryCell {
+                    out_point: OutPoint::new(tx.hash(), index as u32),
+                    output,
+                    data,
+                });
+            }
+        }
+    }
+
+    /// Finds the registry cell covering the wrapped script hash, it is the node lock-wrapper loads to unlock the
+    /// cells of the key: the config applies if the key is the start, otherwise the key is delegated to itself. Like
+    /// `find_registry_node`, the cells not locked by the lock-wrapper of this registry are skipped
+    pub fn lookup(&self, wrapped_hash: &Key) -> Option<(&RegistryCell, Lookup)> {
+        let registry_args = self.registry_type_script.args().raw_data();
+        let registry_hash = self.registry_hash();
+        self.cells.iter().find_map(|cell| {
+            if !is_registry_lock(
+                cell.output.lock().as_slice(),
+                &registry_args,
+                &registry_hash,
+            ) {
+                return None;
+            }
+            let node = cell.node().ok()?;
+            match node.range.lookup(wrapped_hash) {
+                Lookup::Outside => None,
+                lookup => Some((cell, lookup)),
+            }
+        })
+    }
+
+    /// Inserts the key by splitting the node covering it, the config of the node is kept and the key gets no
+    /// override, so its cells are still unlocked by its own wrapped script, which can propose a pending config later.
+    /// The node is unlocked without a proof because its config doesn't change, its requests must be anchored by
+    /// `anchor_requests` first
+    pub fn insert_key(&self, key: Key) -> Result<TransactionView, Error> {
+        let (cell, lookup) = self.lookup(&key).ok_or(Error::KeyNotCovered(key))?;
+        if lookup == Lookup::Start {
+            return Err(Error::KeyExists(key));
+        }
+        let node = cell.node()?;
+        if node.has_unanchored_request() {
+            return Err(Error::UnanchoredRequest(node.range.start));
+        }
+        let (head, head_data) =
+            self.registry_cell(node.range.start, key, &cell.data[KEY_SIZE..])?;
+        let (tail, tail_data) = self.registry_cell(key, node.range.end, &NO_OVERRIDE)?;
+        Ok(self
+            .registry_tx(cell)
+            .outputs([head, tail])
+            .outputs_data([head_data.pack(), tail_data.pack()])
+            .build())
+    }
+
+    /// Removes the key by merging the node starting at it into its predecessor, which keeps its config and
+    /// capacity, the capacity of the removed node is released. The removed node is the first input, the caller adds
+    /// the wrapper witness proving its current owners. The predecessor is unlocked without a proof because its config
+    /// doesn't change, its requests must be anchored by `anchor_requests` first
+    pub fn remove_key(&self, key: Key) -> Result<TransactionView, Error> {
+        let cell = self.find_node(key)?;
+        let node = cell.node()?;
+        let prev_cell = self
+            .cells
+            .iter()
+            .find(|cell| matches!(cell.node(), Ok(prev) if prev.range.end == key))
+            .ok_or(Error::NoPredecessor(key))?;
+        let prev = prev_cell.node()?;
+        if prev.has_unanchored_request() {
+            return Err(Error::UnanchoredRequest(prev.range.start));
+        }
+        let (output, data) = self.registry_cell(
+            prev.range.start,
+            node.range.end,
+            &prev_cell.data[KEY_SIZE..],
+        )?;
+        let capacity: u64 = output.capacity().unpack();
+        let prev_capacity: u64 = prev_cell.output.capacity().unpack();
+        let output = if capacity < prev_capacity {
+            output
+                .as_builder()
+                .capacity(prev_cell.output.capacity())
+                .build()
+        } else {
+            output
+        };
+        Ok(self
+            .registry_tx(cell)
+            .input(prev_cell.input())
+            .output(output)
+            .output_data(data.pack())
+            .build())
+    }
+
+    /// Replaces the config payload of the node starting at the key, the caller adds the wrapper witness proving
+    /// the current wrapped script of the node
+    pub fn update_config(&self, start: Key, config: &[u8]) -> Result<TransactionView, Error> {
+        let cell = self.find_node(start)?;
+        let node = cell.node()?;
+        let (output, data) = self.registry_cell(start, node.range.end, config)?;
+        // keep the capacity of the cell if the new config is shorter
+        let capacity: u64 = output.capacity().unpack();
+        let cell_capacity: u64 = cell.output.capacity().unpack();
+        let output = if capacity < cell_capacity {
+            output.as_builder().capacity(cell.output.capacity()).build()
+        } else {
+            output
+        };
+        Ok(self
+            .registry_tx(cell)
+            .output(output)
+            .output_data(data.pack())
+            .build())
+    }
+
+    /// Anchors the requests of the node starting at the key, `header` is the block of the registry cell which was
+    /// created by the requests. The node is unlocked without a proof because only the since of the requests changes
+    pub fn anchor_requests(
+        &self,
+        start: Key,
+        header: &HeaderView,
+    ) -> Result<TransactionView, Error> {
+        let cell = self.find_node(start)?;
+        let node = cell.node()?;
+        let anchor = |delay| {
+            absolute_since_after(
+                delay,
+                header.number(),
+                header.epoch().full_value(),
+                header.timestamp(),
+            )
+        };
+        let pending = node.config.pending().to_opt().map(|pending| {
+            let since: u64 = pending.since().as_reader().into();
+            match anchor(PENDING_DELAY) {
+                Some(anchor) if since == 0 => pending.as_builder().since(anchor.into()).build(),
+                _ => pending,
+            }
+        });
+        let recovery = node.config.recovery().to_opt().map(|recovery| {
+            let since: u64 = recovery.since().as_reader().into();
+            match node.recovery_request().and_then(|(delay, _)| anchor(delay)) {
+                Some(anchor) if since == 0 => recovery.as_builder().since(anchor.into()).build(),
+                _ => recovery,
+            }
+        });
+        let config = node
+            .config
+            .clone()
+            .as_builder()
+            .pending(schema::PendingConfigOpt::new_builder().set(pending).build())
+            .recovery(
+                schema::RecoveryRequestOpt::new_builder()
+                    .set(recovery)
+                    .build(),
+            )
+            .build();
+        Ok(self
+            .update_config(start, config.as_slice())?
+            .as_advanced_builder()
+            .header_dep(header.hash())
+            .build())
+    }
+
+    /// Unlocks the lock-wrapper cells of the key with the wrapped script, which is the script of the key itself or
+    /// the one configured in the node starting at the key. The first witness is the wrapper witness shared by all the
+    /// inputs, and the inner witness of the wrapped script is at `inner_witness_index`, which must not be below the
+    /// number of the inputs. The caller picks an index after all the inputs it adds later, such as the ones paying
+    /// the fee, and adds their witnesses in the empty slots
+    pub fn build_unlock_tx(
+        &self,
+        key: Key,
+        wrapped_script: &Script,
+        inputs: Vec<CellInput>,
+        inner_witness_index: u16,
+        inner_witness: Bytes,
+    ) -> Result<TransactionView, Error> {
+        if (inner_witness_index as usize) < inputs.len() {
+            return Err(Error::InvalidWitnessIndex(inner_witness_index));
+        }
+        let (cell, _) = self.lookup(&key).ok_or(Error::KeyNotCovered(key))?;
+        let wrapper_witness = schema::WrapperWitness::new_builder()
+            .set(wrapped_proof(wrapped_script, inner_witness_index))
+            .build();
+
+        let mut witnesses = vec![wrapper_witness_args(&wrapper_witness).as_bytes()];
+        witnesses.resize(inner_witness_index as usize, Bytes::new());
+        witnesses.push(inner_witness);
+        Ok(TransactionBuilder::default()
+            .cell_dep(self.lock_wrapper.cell_dep.clone())
+            .cell_dep(cell.cell_dep())
+            .inputs(inputs)
+            .witnesses(witnesses.pack())
+            .build())
+    }
+
+    fn find_node(&self, start: Key) -> Result<&RegistryCell, Error> {
+        self.cells
+            .iter()
+            .find(|cell| matches!(cell.node(), Ok(node) if node.range.start == start))
+            .ok_or(Error::NodeNotFound(start))
+    }
+
+    // a transaction spending the registry cell with the contracts in the cell deps
+    fn registry_tx(&self, cell: &RegistryCell) -> TransactionBuilder {
+        TransactionBuilder::default()
+            .cell_dep(self.global_registry.cell_dep.clone())
+            .cell_dep(self.lock_wrapper.cell_dep.clone())
+            .input(cell.input())
+    }
+}
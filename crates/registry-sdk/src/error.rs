@@ -0,0 +1,70 @@
+use std::fmt;
+
+use ckb_types::core::CapacityError;
+use registry_common::node::{Key, LayoutError};
+
+#[derive(Debug)]
+pub enum Error {
+    /// a registry cell has an invalid layout
+    Layout(LayoutError),
+    /// no known registry cell covers the key
+    KeyNotCovered(Key),
+    /// the key is already the start of a registry node
+    KeyExists(Key),
+    /// no known registry cell starts at the key
+    NodeNotFound(Key),
+    /// the node starting at the key has a request which must be anchored before the node is split or merged
+    UnanchoredRequest(Key),
+    /// the node starting at the key is the sentinel, or the node ending at it is not known
+    NoPredecessor(Key),
+    /// the inner witness would replace the witness of a lock-wrapper input, its index must follow the inputs
+    InvalidWitnessIndex(u16),
+    Capacity(CapacityError),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Layout(err) => write!(f, "invalid registry cell layout: {:?}", err),
+            Error::KeyNotCovered(key) => write!(f, "key {} is not covered", hex(key)),
+            Error::KeyExists(key) => write!(f, "key {} already exists", hex(key)),
+            Error::NodeNotFound(key) => write!(f, "no registry node starts at {}", hex(key)),
+            Error::UnanchoredRequest(key) => {
+                write!(f, "the request of the node at {} is not anchored", hex(key))
+            }
+            Error::NoPredecessor(key) => {
+                write!(f, "no registry node ends at {}", hex(key))
+            }
+            Error::InvalidWitnessIndex(index) => {
+                write!(f, "witness {} is the witness of an unlocked input", index)
+            }
+            Error::Capacity(err) => write!(f, "capacity overflow: {}", err),
+            Error::Json(err) => write!(f, "invalid json: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<LayoutError> for Error {
+    fn from(err: LayoutError) -> Self {
+        Error::Layout(err)
+    }
+}
+
+impl From<CapacityError> for Error {
+    fn from(err: CapacityError) -> Self {
+        Error::Capacity(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+pub(crate) fn hex(key: &Key) -> String {
+    key.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
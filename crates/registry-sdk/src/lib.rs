@@ -0,0 +1,20 @@
+//! Off-chain builders of the global registry and lock-wrapper transactions.
+//!
+//! The transactions built here only contain the registry cells and the cell deps of the contracts,
+//! the callers balance the capacity and add the witnesses of the other inputs.
+
+mod client;
+mod error;
+mod preflight;
+mod state;
+mod witness;
+
+pub use client::{RegistryCell, RegistryClient, ScriptCode};
+pub use error::Error;
+pub use preflight::{CellRef, PreflightError};
+pub use state::{Issue, JsonCell, RegistryState};
+pub use witness::{wrapped_proof, wrapper_witness_args};
+
+pub use ckb_types;
+pub use registry_common;
+pub use registry_common::error::{decode_exit_code, Contract, ErrorInfo};
@@ -0,0 +1,1164 @@
+//! A native pre-flight verifier mirroring the checks of global-registry and lock-wrapper.
+//!
+//! The contracts only report an `i8` exit code, the same checks are run here on a transaction and its resolved
+//! cells, and each failing script group reports which input, output or cell dep and which key are wrong. The
+//! wrapped scripts are not run, and the VM version checks and the since anchored to the block of an input, which
+//! needs its header, are left to the real verification.
+
+use std::fmt;
+
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{Byte, CellOutput, OutPoint, Script, WitnessArgs},
+    prelude::*,
+};
+use registry_common::{
+    argv::ARGV_VERSION_2,
+    error::{Contract, ErrorInfo},
+    hash::{calc_init_hash, calc_script_hash},
+    linked_list::{check_linked_list, sort_ranges, LinkedListError, MAX_NODES_PER_TX},
+    node::{
+        check_request_since, decode_lock_args, decode_range, decode_registry_args,
+        is_config_carried, is_recovery_requested, is_registry_lock, Key, LayoutError, Lookup, Node,
+        Range, RequestSince, KEY_MAX, KEY_MIN, KEY_SIZE, NO_OVERRIDE,
+    },
+    schema::{Byte32Vec, WrappedProof, WrappedProofVec, WrapperWitness, WrapperWitnessUnion},
+};
+
+use crate::{
+    client::{RegistryCell, RegistryClient},
+    error::hex,
+};
+
+/// A cell of the transaction, by its index in the inputs, the outputs or the cell deps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellRef {
+    Input(usize),
+    Output(usize),
+    CellDep(usize),
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellRef::Input(index) => write!(f, "input {}", index),
+            CellRef::Output(index) => write!(f, "output {}", index),
+            CellRef::CellDep(index) => write!(f, "cell dep {}", index),
+        }
+    }
+}
+
+/// A check of the contracts failing on a transaction. The errors of global-registry refer to the registry cells,
+/// the errors of lock-wrapper refer to the first input of the failing lock group as `input`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreflightError {
+    /// the input or the cell dep is not in the resolved cells
+    UnresolvedCell(CellRef),
+
+    // global-registry
+    /// the transaction creating the registry has no input to derive the init hash from
+    MissingInitInput,
+    /// the init hash in the type args of the created registry is not derived from the first input
+    InvalidInitHash {
+        expected: Key,
+    },
+    /// a registry cell can't be decoded
+    InvalidRegistryLayout {
+        cell: CellRef,
+        error: LayoutError,
+    },
+    /// more than one registry cell is created at initialization
+    InvalidInitOutputCount {
+        count: usize,
+    },
+    InvalidSentinelStart {
+        output: usize,
+        start: Key,
+    },
+    InvalidSentinelEnd {
+        output: usize,
+        end: Key,
+    },
+    /// the sentinel is not locked by the lock-wrapper in the type args, or its lock args name another registry
+    InvalidSentinelLock {
+        output: usize,
+    },
+    /// the registry cell exceeds the max number of registry cells in the inputs or outputs
+    TooManyNodes {
+        cell: CellRef,
+    },
+    /// no output continues the linked list at the key
+    MissingOutput {
+        start: Key,
+    },
+    /// the output doesn't start where the previous one ends
+    UnexpectedOutputStart {
+        output: usize,
+        start: Key,
+        expected: Key,
+    },
+    EmptyOutput {
+        output: usize,
+        start: Key,
+    },
+    /// the output ends after the last input
+    OutputBeyondInputs {
+        output: usize,
+        end: Key,
+    },
+    /// the input merged by an output doesn't start where the previous input ends
+    NonConsecutiveInputs {
+        input: usize,
+        start: Key,
+        expected: Key,
+    },
+    /// the output is not covered by the inputs
+    ExtraOutput {
+        output: usize,
+        start: Key,
+    },
+    /// the output inserted by a split overrides the wrapped script of its start key
+    InvalidInsertedConfig {
+        output: usize,
+        start: Key,
+    },
+    /// the output is not locked by the lock-wrapper in the type args, or its lock args name another registry
+    InvalidNodeLock {
+        output: usize,
+    },
+
+    // lock-wrapper
+    InvalidLockArgs {
+        input: usize,
+    },
+    /// no registry cell of the registry is in the cell deps
+    RegistryCellDepNotFound {
+        input: usize,
+    },
+    /// no registry cell in the cell deps covers the key
+    KeyNotCovered {
+        input: usize,
+        key: Key,
+    },
+    /// the registry node loaded by lock-wrapper can't be decoded
+    InvalidNodeLayout {
+        cell: CellRef,
+        error: LayoutError,
+    },
+    /// the lock group spends other cells with its registry cell, or several registry cells, `count` is the number of
+    /// the inputs of the group
+    InvalidInputCount {
+        input: usize,
+        count: usize,
+    },
+    /// the registry cell of the lock group has several successors locked by the same script
+    InvalidOutputCount {
+        input: usize,
+        count: usize,
+    },
+    /// the registry cell of the lock group is removed without being merged into a registry cell of the same
+    /// lock-wrapper starting before it
+    NotMerged {
+        input: usize,
+    },
+    InvalidWitnessFormat {
+        input: usize,
+    },
+    InvalidWrapperWitness {
+        input: usize,
+    },
+    MissingWrapperWitness {
+        input: usize,
+    },
+    /// the proof of the input is not of the wrapped script selected by the registry
+    WrongWrappedScript {
+        input: usize,
+        expected: Key,
+        actual: Key,
+    },
+    InvalidWrappedScriptHashType {
+        input: usize,
+        hash_type: u8,
+    },
+    UnsupportedArgvVersion {
+        input: usize,
+        version: u8,
+    },
+    /// the owners of the node are changed without an activated pending config
+    InvalidConfigChange {
+        input: usize,
+    },
+    MissingGuardianSet {
+        input: usize,
+    },
+    GuardianDelayNotReached {
+        input: usize,
+    },
+    /// the proof at the index is not of a listed script, or the script is proved twice
+    InvalidMultiProof {
+        input: usize,
+        proof: usize,
+    },
+    ThresholdNotReached {
+        input: usize,
+        proved: usize,
+        threshold: u8,
+    },
+    /// a new request of the node has a non-zero since, or a carried one changes its since
+    InvalidRequestSince {
+        input: usize,
+    },
+    /// the guardians change the config when requesting the recovery, or keep the request when executing it
+    InvalidRecovery {
+        input: usize,
+    },
+    /// the proof of the input asks for another argv version than the first proof of the group
+    MixedArgvVersions {
+        input: usize,
+    },
+}
+
+impl From<LinkedListError> for PreflightError {
+    fn from(err: LinkedListError) -> Self {
+        match err {
+            LinkedListError::MissingOutput { start } => PreflightError::MissingOutput { start },
+            LinkedListError::UnexpectedOutputStart {
+                output,
+                start,
+                expected,
+            } => PreflightError::UnexpectedOutputStart {
+                output,
+                start,
+                expected,
+            },
+            LinkedListError::EmptyOutput { output, start } => {
+                PreflightError::EmptyOutput { output, start }
+            }
+            LinkedListError::OutputBeyondInputs { output, end } => {
+                PreflightError::OutputBeyondInputs { output, end }
+            }
+            LinkedListError::NonConsecutiveInputs {
+                input,
+                start,
+                expected,
+            } => PreflightError::NonConsecutiveInputs {
+                input,
+                start,
+                expected,
+            },
+            LinkedListError::ExtraOutput { output, start } => {
+                PreflightError::ExtraOutput { output, start }
+            }
+        }
+    }
+}
+
+impl PreflightError {
+    /// The error of the contract failing on the check, None if it is not a check of the contracts
+    pub fn error_info(&self) -> Option<ErrorInfo> {
+        use PreflightError::*;
+        let (contract, name) = match self {
+            UnresolvedCell(_) => return None,
+            MissingInitInput => (Contract::GlobalRegistry, "IndexOutOfBound"),
+            InvalidInitHash { .. } => (Contract::GlobalRegistry, "InvalidInitHash"),
+            InvalidRegistryLayout { error, .. } => match error {
+                LayoutError::InvalidArgs => (Contract::GlobalRegistry, "InvalidArgsLength"),
+                LayoutError::InvalidData | LayoutError::InvalidConfig => {
+                    (Contract::GlobalRegistry, "InvalidDataLength")
+                }
+            },
+            MissingOutput { .. }
+            | UnexpectedOutputStart { .. }
+            | EmptyOutput { .. }
+            | OutputBeyondInputs { .. }
+            | NonConsecutiveInputs { .. }
+            | ExtraOutput { .. } => (Contract::GlobalRegistry, "InvalidLinkedList"),
+            InvalidInitOutputCount { .. } => (Contract::GlobalRegistry, "InvalidInitOutputCount"),
+            InvalidSentinelStart { .. } => (Contract::GlobalRegistry, "InvalidSentinelStart"),
+            InvalidSentinelEnd { .. } => (Contract::GlobalRegistry, "InvalidSentinelEnd"),
+            InvalidSentinelLock { .. } => (Contract::GlobalRegistry, "InvalidSentinelLock"),
+            TooManyNodes { .. } => (Contract::GlobalRegistry, "TooManyNodes"),
+            InvalidInsertedConfig { .. } => (Contract::GlobalRegistry, "InvalidInsertedConfig"),
+            InvalidNodeLock { .. } => (Contract::GlobalRegistry, "InvalidNodeLock"),
+
+            RegistryCellDepNotFound { .. } => (Contract::LockWrapper, "InvalidCellDepTypeScript"),
+            InvalidNodeLayout { error, .. } => match error {
+                LayoutError::InvalidData => (Contract::LockWrapper, "InvalidDataLength"),
+                LayoutError::InvalidArgs => (Contract::LockWrapper, "InvalidArgsLength"),
+                LayoutError::InvalidConfig => (Contract::LockWrapper, "InvalidNodeConfig"),
+            },
+            InvalidLockArgs { .. } => (Contract::LockWrapper, "InvalidArgsLength"),
+            KeyNotCovered { .. } => (Contract::LockWrapper, "InvalidCellDepRef"),
+            InvalidInputCount { .. } => (Contract::LockWrapper, "InvalidInputCount"),
+            InvalidOutputCount { .. } | NotMerged { .. } => {
+                (Contract::LockWrapper, "InvalidOutputLockScript")
+            }
+            InvalidWitnessFormat { .. } => (Contract::LockWrapper, "InvalidWitnessFormat"),
+            WrongWrappedScript { .. } => (Contract::LockWrapper, "InvalidWrappedScriptHash"),
+            InvalidWrappedScriptHashType { .. } => {
+                (Contract::LockWrapper, "InvalidWrappedScriptHashType")
+            }
+            InvalidWrapperWitness { .. } => (Contract::LockWrapper, "InvalidWrapperWitness"),
+            MissingWrapperWitness { .. } => (Contract::LockWrapper, "MissingWrapperWitness"),
+            InvalidConfigChange { .. } => (Contract::LockWrapper, "InvalidConfigChange"),
+            MissingGuardianSet { .. } => (Contract::LockWrapper, "MissingGuardianSet"),
+            GuardianDelayNotReached { .. } => (Contract::LockWrapper, "GuardianDelayNotReached"),
+            InvalidMultiProof { .. } => (Contract::LockWrapper, "InvalidMultiProof"),
+            ThresholdNotReached { .. } => (Contract::LockWrapper, "ThresholdNotReached"),
+            UnsupportedArgvVersion { .. } => (Contract::LockWrapper, "UnsupportedArgvVersion"),
+            InvalidRequestSince { .. } => (Contract::LockWrapper, "InvalidRequestSince"),
+            InvalidRecovery { .. } => (Contract::LockWrapper, "InvalidRecovery"),
+            MixedArgvVersions { .. } => (Contract::LockWrapper, "MixedArgvVersions"),
+        };
+        Some(contract.find_error(name).expect("error of the contract"))
+    }
+
+    /// The exit code of the contract failing on the check
+    pub fn exit_code(&self) -> Option<i8> {
+        self.error_info().map(|info| info.code)
+    }
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PreflightError::*;
+        match self {
+            UnresolvedCell(cell) => write!(f, "{} is not resolved", cell),
+            MissingInitInput => write!(
+                f,
+                "global-registry: the new registry has no input to derive its init hash from"
+            ),
+            InvalidInitHash { expected } => write!(
+                f,
+                "global-registry: the init hash of the new registry must be {}",
+                hex(expected)
+            ),
+            InvalidRegistryLayout { cell, error } => write!(
+                f,
+                "global-registry: registry cell {} has an invalid layout: {:?}",
+                cell, error
+            ),
+            InvalidInitOutputCount { count } => write!(
+                f,
+                "global-registry: {} registry cells are created at initialization, only the sentinel is allowed",
+                count
+            ),
+            InvalidSentinelStart { output, start } => write!(
+                f,
+                "global-registry: the sentinel output {} starts at {} instead of the min key",
+                output,
+                hex(start)
+            ),
+            InvalidSentinelEnd { output, end } => write!(
+                f,
+                "global-registry: the sentinel output {} ends at {} instead of the max key",
+                output,
+                hex(end)
+            ),
+            InvalidSentinelLock { output } => write!(
+                f,
+                "global-registry: the sentinel output {} is not locked by the lock-wrapper of this registry",
+                output
+            ),
+            TooManyNodes { cell } => write!(
+                f,
+                "global-registry: registry cell {} exceeds the limit of {} registry cells",
+                cell, MAX_NODES_PER_TX
+            ),
+            MissingOutput { start } => write!(
+                f,
+                "global-registry: no output continues the linked list at {}",
+                hex(start)
+            ),
+            UnexpectedOutputStart {
+                output,
+                start,
+                expected,
+            } => write!(
+                f,
+                "global-registry: output {} starts at {} instead of {}",
+                output,
+                hex(start),
+                hex(expected)
+            ),
+            EmptyOutput { output, start } => write!(
+                f,
+                "global-registry: output {} has an empty range at {}",
+                output,
+                hex(start)
+            ),
+            OutputBeyondInputs { output, end } => write!(
+                f,
+                "global-registry: output {} ends at {} after the last input",
+                output,
+                hex(end)
+            ),
+            NonConsecutiveInputs {
+                input,
+                start,
+                expected,
+            } => write!(
+                f,
+                "global-registry: input {} starts at {} instead of {}, the inputs are not consecutive",
+                input,
+                hex(start),
+                hex(expected)
+            ),
+            ExtraOutput { output, start } => write!(
+                f,
+                "global-registry: output {} at {} is not covered by the inputs",
+                output,
+                hex(start)
+            ),
+            InvalidInsertedConfig { output, start } => write!(
+                f,
+                "global-registry: output {} inserts {} with an override, the key must propose its own config",
+                output,
+                hex(start)
+            ),
+            InvalidNodeLock { output } => write!(
+                f,
+                "global-registry: output {} is not locked by the lock-wrapper of this registry",
+                output
+            ),
+
+            InvalidLockArgs { input } => {
+                write!(f, "lock-wrapper of input {}: invalid lock args", input)
+            }
+            RegistryCellDepNotFound { input } => write!(
+                f,
+                "lock-wrapper of input {}: no registry cell in the cell deps",
+                input
+            ),
+            KeyNotCovered { input, key } => write!(
+                f,
+                "lock-wrapper of input {}: no registry cell in the cell deps covers {}",
+                input,
+                hex(key)
+            ),
+            InvalidNodeLayout { cell, error } => write!(
+                f,
+                "lock-wrapper: registry cell {} has an invalid layout: {:?}",
+                cell, error
+            ),
+            InvalidInputCount { input, count } => write!(
+                f,
+                "lock-wrapper of input {}: the group spends {} cells instead of its registry cell only",
+                input, count
+            ),
+            InvalidOutputCount { input, count } => write!(
+                f,
+                "lock-wrapper of input {}: {} registry outputs are locked by the group instead of at most one",
+                input, count
+            ),
+            NotMerged { input } => write!(
+                f,
+                "lock-wrapper of input {}: the removed node is not merged into a registry cell of the same lock-wrapper",
+                input
+            ),
+            InvalidWitnessFormat { input } => write!(
+                f,
+                "lock-wrapper: the witness of input {} is not a WitnessArgs",
+                input
+            ),
+            InvalidWrapperWitness { input } => write!(
+                f,
+                "lock-wrapper: the witness of input {} has an invalid or unexpected wrapper witness",
+                input
+            ),
+            MissingWrapperWitness { input } => write!(
+                f,
+                "lock-wrapper: the first input {} of the group has no wrapper witness",
+                input
+            ),
+            WrongWrappedScript {
+                input,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "lock-wrapper: input {} proves the wrapped script {} instead of {}",
+                input,
+                hex(actual),
+                hex(expected)
+            ),
+            InvalidWrappedScriptHashType { input, hash_type } => write!(
+                f,
+                "lock-wrapper of input {}: invalid hash type {} of the wrapped script",
+                input, hash_type
+            ),
+            UnsupportedArgvVersion { input, version } => write!(
+                f,
+                "lock-wrapper of input {}: unsupported argv version {}",
+                input, version
+            ),
+            InvalidConfigChange { input } => write!(
+                f,
+                "lock-wrapper of input {}: the owners of the node can only be changed by an activated pending config",
+                input
+            ),
+            MissingGuardianSet { input } => write!(
+                f,
+                "lock-wrapper of input {}: the node has no guardians",
+                input
+            ),
+            GuardianDelayNotReached { input } => write!(
+                f,
+                "lock-wrapper of input {}: the recovery is not requested the guardian delay before",
+                input
+            ),
+            InvalidMultiProof { input, proof } => write!(
+                f,
+                "lock-wrapper of input {}: proof {} is not of a listed script or is a duplicate",
+                input, proof
+            ),
+            ThresholdNotReached {
+                input,
+                proved,
+                threshold,
+            } => write!(
+                f,
+                "lock-wrapper of input {}: {} scripts are proved, the threshold is {}",
+                input, proved, threshold
+            ),
+            InvalidRequestSince { input } => write!(
+                f,
+                "lock-wrapper of input {}: a new request must have a zero since and a carried one keeps its since",
+                input
+            ),
+            InvalidRecovery { input } => write!(
+                f,
+                "lock-wrapper of input {}: the guardians must request the recovery alone and clear it when executing it",
+                input
+            ),
+            MixedArgvVersions { input } => write!(
+                f,
+                "lock-wrapper of input {}: the proof asks for another argv version than the first proof of the group",
+                input
+            ),
+        }
+    }
+}
+
+// the transaction with its inputs and cell deps resolved
+struct ResolvedTx<'a> {
+    tx: &'a TransactionView,
+    inputs: Vec<(CellOutput, Bytes)>,
+    outputs: Vec<(CellOutput, Bytes)>,
+    cell_deps: Vec<(CellOutput, Bytes)>,
+}
+
+impl<'a> ResolvedTx<'a> {
+    fn new(tx: &'a TransactionView, cells: &[RegistryCell]) -> Result<Self, Vec<PreflightError>> {
+        let resolve = |out_point: OutPoint| {
+            cells
+                .iter()
+                .find(|cell| cell.out_point == out_point)
+                .map(|cell| (cell.output.clone(), cell.data.clone()))
+        };
+        let mut errors = Vec::new();
+        let mut inputs = Vec::new();
+        for (index, out_point) in tx.input_pts_iter().enumerate() {
+            match resolve(out_point) {
+                Some(cell) => inputs.push(cell),
+                None => errors.push(PreflightError::UnresolvedCell(CellRef::Input(index))),
+            }
+        }
+        let mut cell_deps = Vec::new();
+        for (index, cell_dep) in tx.cell_deps_iter().enumerate() {
+            match resolve(cell_dep.out_point()) {
+                Some(cell) => cell_deps.push(cell),
+                None => errors.push(PreflightError::UnresolvedCell(CellRef::CellDep(index))),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(ResolvedTx {
+            tx,
+            inputs,
+            outputs: tx.outputs_with_data_iter().collect(),
+            cell_deps,
+        })
+    }
+
+    // the witness of the input, None if it is absent or empty like `load_wrapper_witness`
+    fn wrapper_witness(&self, input: usize) -> Result<Option<WrapperWitnessUnion>, PreflightError> {
+        let witness = match self.tx.witnesses().get(input) {
+            Some(witness) => witness.raw_data(),
+            None => return Ok(None),
+        };
+        if witness.is_empty() {
+            return Ok(None);
+        }
+        let witness_args = WitnessArgs::from_slice(&witness)
+            .map_err(|_| PreflightError::InvalidWitnessFormat { input })?;
+        match witness_args.lock().to_opt() {
+            Some(lock) => WrapperWitness::from_compatible_slice(&lock.raw_data())
+                .map(|witness| Some(witness.to_enum()))
+                .map_err(|_| PreflightError::InvalidWrapperWitness { input }),
+            None => Ok(None),
+        }
+    }
+
+    fn input_since(&self, input: usize) -> Result<u64, PreflightError> {
+        self.tx
+            .inputs()
+            .get(input)
+            .map(|input| input.since().unpack())
+            .ok_or(PreflightError::UnresolvedCell(CellRef::Input(input)))
+    }
+}
+
+// the inputs locked by the same lock-wrapper script, by their indexes in the transaction
+struct LockGroup {
+    script: Script,
+    inputs: Vec<usize>,
+}
+
+impl LockGroup {
+    fn first_input(&self) -> usize {
+        self.inputs[0]
+    }
+
+    fn inputs_since(&self, tx: &ResolvedTx) -> Result<Vec<u64>, PreflightError> {
+        self.inputs
+            .iter()
+            .map(|input| tx.input_since(*input))
+            .collect()
+    }
+}
+
+impl RegistryClient {
+    /// Runs the checks of the contracts on the transaction natively, `cells` resolves its inputs and cell deps, the
+    /// cell deps of dep groups are not expanded. The registry type script and the lock-wrapper groups of the
+    /// registry are checked, each of them reports its first error like the contracts do
+    pub fn preflight(&self, tx: &TransactionView, cells: &[RegistryCell]) -> Vec<PreflightError> {
+        let tx = match ResolvedTx::new(tx, cells) {
+            Ok(tx) => tx,
+            Err(errors) => return errors,
+        };
+        let mut errors = Vec::new();
+        for group in self.lock_wrapper_groups(&tx) {
+            if let Err(err) = self.check_lock_wrapper(&tx, &group) {
+                errors.push(err);
+            }
+        }
+        if let Err(err) = self.check_global_registry(&tx) {
+            errors.push(err);
+        }
+        errors
+    }
+
+    fn check_global_registry(&self, tx: &ResolvedTx) -> Result<(), PreflightError> {
+        let inputs = registry_indexes(&tx.inputs, self.registry_type_script());
+        let outputs = registry_indexes(&tx.outputs, self.registry_type_script());
+        if inputs.is_empty() && outputs.is_empty() {
+            return Ok(());
+        }
+        if inputs.is_empty() {
+            self.check_init(tx, &outputs)
+        } else {
+            let inputs = sorted_ranges(&tx.inputs, &inputs, CellRef::Input)?;
+            let ranges = sorted_ranges(&tx.outputs, &outputs, CellRef::Output)?;
+            for (output, start) in check_linked_list(&inputs, &ranges)? {
+                let (_, data) = &tx.outputs[output];
+                if data[KEY_SIZE..] != NO_OVERRIDE {
+                    return Err(PreflightError::InvalidInsertedConfig { output, start });
+                }
+            }
+            // `validate_node_locks`
+            match outputs
+                .into_iter()
+                .find(|output| !self.is_node_locked(tx, *output))
+            {
+                Some(output) => Err(PreflightError::InvalidNodeLock { output }),
+                None => Ok(()),
+            }
+        }
+    }
+
+    // `is_node_locked`
+    fn is_node_locked(&self, tx: &ResolvedTx, output: usize) -> bool {
+        is_registry_lock(
+            tx.outputs[output].0.lock().as_slice(),
+            &self.registry_type_script().args().raw_data(),
+            &self.registry_hash(),
+        )
+    }
+
+    // `validate_init_hash` and `validate_sentinel`
+    fn check_init(&self, tx: &ResolvedTx, outputs: &[usize]) -> Result<(), PreflightError> {
+        let first_input = tx
+            .tx
+            .inputs()
+            .get(0)
+            .ok_or(PreflightError::MissingInitInput)?;
+        let expected = calc_init_hash(first_input.as_slice(), outputs[0] as u64);
+        let registry_args = decode_registry_args(&self.registry_type_script().args().raw_data());
+        if !matches!(registry_args, Ok((init_hash, _, _)) if init_hash == expected) {
+            return Err(PreflightError::InvalidInitHash { expected });
+        }
+
+        let output = outputs[0];
+        let range = load_range(&tx.outputs, output).map_err(|error| {
+            PreflightError::InvalidRegistryLayout {
+                cell: CellRef::Output(output),
+                error,
+            }
+        })?;
+        if outputs.len() != 1 {
+            return Err(PreflightError::InvalidInitOutputCount {
+                count: outputs.len(),
+            });
+        }
+        if range.start != KEY_MIN {
+            return Err(PreflightError::InvalidSentinelStart {
+                output,
+                start: range.start,
+            });
+        }
+        if range.end != KEY_MAX {
+            return Err(PreflightError::InvalidSentinelEnd {
+                output,
+                end: range.end,
+            });
+        }
+        if !self.is_node_locked(tx, output) {
+            return Err(PreflightError::InvalidSentinelLock { output });
+        }
+        Ok(())
+    }
+
+    // the inputs locked by lock-wrapper grouped by lock script, the groups of other registries are skipped
+    fn lock_wrapper_groups(&self, tx: &ResolvedTx) -> Vec<LockGroup> {
+        let lock_wrapper = self.lock_wrapper();
+        let hash_type: Byte = lock_wrapper.hash_type.into();
+        let mut groups: Vec<LockGroup> = Vec::new();
+        for (index, (output, _)) in tx.inputs.iter().enumerate() {
+            let lock = output.lock();
+            if lock.code_hash() != lock_wrapper.code_hash || lock.hash_type() != hash_type {
+                continue;
+            }
+            if let Ok((registry_hash, _)) = decode_lock_args(&lock.args().raw_data()) {
+                if registry_hash != self.registry_hash() {
+                    continue;
+                }
+            }
+            match groups.iter_mut().find(|group| group.script == lock) {
+                Some(group) => group.inputs.push(index),
+                None => groups.push(LockGroup {
+                    script: lock,
+                    inputs: vec![index],
+                }),
+            }
+        }
+        groups
+    }
+
+    fn check_lock_wrapper(&self, tx: &ResolvedTx, group: &LockGroup) -> Result<(), PreflightError> {
+        let (_, key) = decode_lock_args(&group.script.args().raw_data()).map_err(|_| {
+            PreflightError::InvalidLockArgs {
+                input: group.first_input(),
+            }
+        })?;
+        let registry_inputs: Vec<usize> = group
+            .inputs
+            .iter()
+            .copied()
+            .filter(|input| is_registry_cell(&tx.inputs[*input].0, self.registry_type_script()))
+            .collect();
+        if registry_inputs.is_empty() {
+            self.check_delegate(tx, group, key)
+        } else {
+            self.check_config_value(tx, group, &registry_inputs)
+        }
+    }
+
+    // `exec_wrapped_script`: the cells of the key are unlocked by the wrapped script selected by the registry node
+    // covering it in the cell deps
+    fn check_delegate(
+        &self,
+        tx: &ResolvedTx,
+        group: &LockGroup,
+        key: Key,
+    ) -> Result<(), PreflightError> {
+        let node = self.find_registry_node(tx, group, key)?;
+        let inputs_since = group.inputs_since(tx)?;
+        match node.range.lookup(&key) {
+            Lookup::Start if node.is_pending_activated(&inputs_since, None) => {
+                check_wrapped_proofs(
+                    tx,
+                    group,
+                    node.active_wrapped_script_hash(&inputs_since, None),
+                )
+            }
+            Lookup::Start => check_current_owners(tx, group, &node),
+            Lookup::Inside => check_wrapped_proofs(tx, group, key),
+            Lookup::Outside => Err(PreflightError::KeyNotCovered {
+                input: group.first_input(),
+                key,
+            }),
+        }
+    }
+
+    // `find_registry_node`
+    fn find_registry_node(
+        &self,
+        tx: &ResolvedTx,
+        group: &LockGroup,
+        key: Key,
+    ) -> Result<Node, PreflightError> {
+        let mut found_registry_cell = false;
+        for (index, (output, data)) in tx.cell_deps.iter().enumerate() {
+            if !is_registry_cell(output, self.registry_type_script()) {
+                continue;
+            }
+            found_registry_cell = true;
+
+            let lock = output.lock();
+            if lock.code_hash() != group.script.code_hash()
+                || lock.hash_type() != group.script.hash_type()
+            {
+                continue;
+            }
+            let args = lock.args().raw_data();
+            let invalid_layout = |error| PreflightError::InvalidNodeLayout {
+                cell: CellRef::CellDep(index),
+                error,
+            };
+            let range = match decode_range(&args, data) {
+                Ok(range) => range,
+                Err(LayoutError::InvalidArgs) => continue,
+                Err(error) => return Err(invalid_layout(error)),
+            };
+            let registry_hash = decode_lock_args(&args).map(|(hash, _)| hash);
+            if registry_hash != Ok(self.registry_hash()) {
+                continue;
+            }
+            if range.lookup(&key) != Lookup::Outside {
+                return Node::from_slices(&args, data).map_err(invalid_layout);
+            }
+        }
+
+        if found_registry_cell {
+            Err(PreflightError::KeyNotCovered {
+                input: group.first_input(),
+                key,
+            })
+        } else {
+            Err(PreflightError::RegistryCellDepNotFound {
+                input: group.first_input(),
+            })
+        }
+    }
+
+    // `validate_merge`
+    fn check_merge(
+        &self,
+        tx: &ResolvedTx,
+        group: &LockGroup,
+        node: &Node,
+    ) -> Result<(), PreflightError> {
+        for output in registry_indexes(&tx.outputs, self.registry_type_script()) {
+            let lock = tx.outputs[output].0.lock();
+            if lock.code_hash() != group.script.code_hash()
+                || lock.hash_type() != group.script.hash_type()
+            {
+                continue;
+            }
+            let range = match load_range(&tx.outputs, output) {
+                Ok(range) => range,
+                Err(_) => continue,
+            };
+            let registry_hash = decode_lock_args(&lock.args().raw_data()).map(|(hash, _)| hash);
+            if registry_hash == Ok(self.registry_hash())
+                && range.lookup(&node.range.start) == Lookup::Inside
+            {
+                return Ok(());
+            }
+        }
+        Err(PreflightError::NotMerged {
+            input: group.first_input(),
+        })
+    }
+
+    // `validate_config_value`: the registry cell of the group is updated by its owners
+    fn check_config_value(
+        &self,
+        tx: &ResolvedTx,
+        group: &LockGroup,
+        registry_inputs: &[usize],
+    ) -> Result<(), PreflightError> {
+        let input = group.first_input();
+        let input_index = match registry_inputs {
+            [index] if group.inputs.len() == 1 => *index,
+            _ => {
+                return Err(PreflightError::InvalidInputCount {
+                    input,
+                    count: group.inputs.len(),
+                })
+            }
+        };
+        let args = group.script.args().raw_data();
+        let input_node = Node::from_slices(&args, &tx.inputs[input_index].1).map_err(|error| {
+            PreflightError::InvalidNodeLayout {
+                cell: CellRef::Input(input_index),
+                error,
+            }
+        })?;
+        let outputs: Vec<usize> = registry_indexes(&tx.outputs, self.registry_type_script())
+            .into_iter()
+            .filter(|output| tx.outputs[*output].0.lock() == group.script)
+            .collect();
+        let output_index = match outputs.as_slice() {
+            // the node is merged into its predecessor
+            [] => {
+                self.check_merge(tx, group, &input_node)?;
+                return check_node_removal(tx, group, &input_node, input_index);
+            }
+            [index] => *index,
+            _ => {
+                return Err(PreflightError::InvalidOutputCount {
+                    input,
+                    count: outputs.len(),
+                })
+            }
+        };
+        let output_node =
+            Node::from_slices(&args, &tx.outputs[output_index].1).map_err(|error| {
+                PreflightError::InvalidNodeLayout {
+                    cell: CellRef::Output(output_index),
+                    error,
+                }
+            })?;
+        // `validate_request_since`, the anchored since is not checked without the header of the input
+        if check_request_since(input_node.pending_request(), output_node.pending_request())
+            == RequestSince::Invalid
+            || check_request_since(
+                input_node.recovery_request(),
+                output_node.recovery_request(),
+            ) == RequestSince::Invalid
+        {
+            return Err(PreflightError::InvalidRequestSince { input });
+        }
+        if is_config_carried(&input_node.config, &output_node.config) {
+            return Ok(());
+        }
+
+        if let Some(WrapperWitnessUnion::GuardianProof(guardian_proof)) =
+            tx.wrapper_witness(input)?
+        {
+            let guardians = input_node
+                .config
+                .guardians()
+                .to_opt()
+                .ok_or(PreflightError::MissingGuardianSet { input })?;
+            if input_node.recovery_request().is_none() {
+                if !is_recovery_requested(&input_node.config, &output_node.config) {
+                    return Err(PreflightError::InvalidRecovery { input });
+                }
+            } else if !input_node
+                .is_recovery_activated(&group.inputs_since(tx)?, Some(tx.input_since(input_index)?))
+            {
+                return Err(PreflightError::GuardianDelayNotReached { input });
+            } else if output_node.config.recovery().is_some() {
+                return Err(PreflightError::InvalidRecovery { input });
+            }
+            let proofs = check_threshold_proofs(
+                input,
+                guardians.guardian_hashes(),
+                guardians.threshold().as_slice()[0],
+                guardian_proof.proofs(),
+            )?;
+            return check_proofs_runnable(input, &proofs);
+        }
+
+        let inputs_since = group.inputs_since(tx)?;
+        let registry_input_since = Some(tx.input_since(input_index)?);
+        if input_node.is_pending_activated(&inputs_since, registry_input_since) {
+            check_wrapped_proofs(
+                tx,
+                group,
+                input_node.active_wrapped_script_hash(&inputs_since, registry_input_since),
+            )
+        } else if input_node.config.wrapped_script_hash().as_slice()
+            == output_node.config.wrapped_script_hash().as_slice()
+            && input_node.config.threshold_scripts().as_slice()
+                == output_node.config.threshold_scripts().as_slice()
+        {
+            check_current_owners(tx, group, &input_node)
+        } else {
+            Err(PreflightError::InvalidConfigChange { input })
+        }
+    }
+}
+
+fn is_registry_cell(output: &CellOutput, registry_type_script: &Script) -> bool {
+    output.type_().to_opt().as_ref() == Some(registry_type_script)
+}
+
+fn registry_indexes(cells: &[(CellOutput, Bytes)], registry_type_script: &Script) -> Vec<usize> {
+    cells
+        .iter()
+        .enumerate()
+        .filter(|(_, (output, _))| is_registry_cell(output, registry_type_script))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn load_range(cells: &[(CellOutput, Bytes)], index: usize) -> Result<Range, LayoutError> {
+    let (output, data) = &cells[index];
+    decode_range(&output.lock().args().raw_data(), data)
+}
+
+// `load_sorted_ranges`, the ranges are paired with the indexes of the cells in the transaction
+fn sorted_ranges(
+    cells: &[(CellOutput, Bytes)],
+    indexes: &[usize],
+    cell_ref: fn(usize) -> CellRef,
+) -> Result<Vec<(usize, Range)>, PreflightError> {
+    let mut ranges = Vec::new();
+    for index in indexes {
+        let range =
+            load_range(cells, *index).map_err(|error| PreflightError::InvalidRegistryLayout {
+                cell: cell_ref(*index),
+                error,
+            })?;
+        if ranges.len() == MAX_NODES_PER_TX {
+            return Err(PreflightError::TooManyNodes {
+                cell: cell_ref(*index),
+            });
+        }
+        ranges.push((*index, range));
+    }
+    sort_ranges(&mut ranges);
+    Ok(ranges)
+}
+
+// `validate_node_removal`
+fn check_node_removal(
+    tx: &ResolvedTx,
+    group: &LockGroup,
+    node: &Node,
+    input_index: usize,
+) -> Result<(), PreflightError> {
+    let inputs_since = group.inputs_since(tx)?;
+    let registry_input_since = Some(tx.input_since(input_index)?);
+    if node.is_pending_activated(&inputs_since, registry_input_since) {
+        check_wrapped_proofs(
+            tx,
+            group,
+            node.active_wrapped_script_hash(&inputs_since, registry_input_since),
+        )
+    } else {
+        check_current_owners(tx, group, node)
+    }
+}
+
+// `exec_current_wrapped_scripts`
+fn check_current_owners(
+    tx: &ResolvedTx,
+    group: &LockGroup,
+    node: &Node,
+) -> Result<(), PreflightError> {
+    let input = group.first_input();
+    match node.config.threshold_scripts().to_opt() {
+        Some(threshold_scripts) => {
+            let multi_proof = match tx.wrapper_witness(input)? {
+                Some(WrapperWitnessUnion::MultiProof(multi_proof)) => multi_proof,
+                _ => return Err(PreflightError::InvalidWrapperWitness { input }),
+            };
+            let proofs = check_threshold_proofs(
+                input,
+                threshold_scripts.wrapped_script_hashes(),
+                threshold_scripts.threshold().as_slice()[0],
+                multi_proof.proofs(),
+            )?;
+            check_proofs_runnable(input, &proofs)
+        }
+        None => check_wrapped_proofs(tx, group, node.wrapped_script_hash()),
+    }
+}
+
+// `verify_threshold_proofs`
+fn check_threshold_proofs(
+    input: usize,
+    hashes: Byte32Vec,
+    threshold: u8,
+    proofs: WrappedProofVec,
+) -> Result<Vec<WrappedProof>, PreflightError> {
+    let hashes: Vec<Key> = hashes.into_iter().map(Into::into).collect();
+    let mut proved_hashes = Vec::new();
+    let mut verified_proofs = Vec::new();
+    for (index, proof) in proofs.into_iter().enumerate() {
+        let hash = calc_script_hash(proof.wrapped_script().as_slice());
+        if !hashes.contains(&hash) || proved_hashes.contains(&hash) {
+            return Err(PreflightError::InvalidMultiProof {
+                input,
+                proof: index,
+            });
+        }
+        proved_hashes.push(hash);
+        verified_proofs.push(proof);
+    }
+
+    if threshold == 0 || verified_proofs.len() < threshold as usize {
+        return Err(PreflightError::ThresholdNotReached {
+            input,
+            proved: verified_proofs.len(),
+            threshold,
+        });
+    }
+    Ok(verified_proofs)
+}
+
+// `load_wrapped_script_proofs`, the first group input presents a proof of the wrapped script and the others may
+// present their own
+fn check_wrapped_proofs(
+    tx: &ResolvedTx,
+    group: &LockGroup,
+    wrapped_script_hash: Key,
+) -> Result<(), PreflightError> {
+    let mut first_proof: Option<WrappedProof> = None;
+    for (index, input) in group.inputs.iter().copied().enumerate() {
+        let proof = match tx.wrapper_witness(input)? {
+            Some(WrapperWitnessUnion::WrappedProof(proof)) => proof,
+            Some(_) => return Err(PreflightError::InvalidWrapperWitness { input }),
+            None if index > 0 => continue,
+            None => return Err(PreflightError::MissingWrapperWitness { input }),
+        };
+        let hash = calc_script_hash(proof.wrapped_script().as_slice());
+        if hash != wrapped_script_hash {
+            return Err(PreflightError::WrongWrappedScript {
+                input,
+                expected: wrapped_script_hash,
+                actual: hash,
+            });
+        }
+        if matches!(&first_proof, Some(first) if first.argv_version() != proof.argv_version()) {
+            return Err(PreflightError::MixedArgvVersions { input });
+        }
+        first_proof.get_or_insert(proof);
+    }
+    check_proofs_runnable(
+        group.first_input(),
+        &first_proof.into_iter().collect::<Vec<_>>(),
+    )
+}
+
+// the checks of `run_wrapped_script` before the wrapped script runs
+fn check_proofs_runnable(input: usize, proofs: &[WrappedProof]) -> Result<(), PreflightError> {
+    for proof in proofs {
+        let hash_type = proof.wrapped_script().hash_type().as_slice()[0];
+        if !matches!(hash_type, 0 | 1 | 2 | 4) {
+            return Err(PreflightError::InvalidWrappedScriptHashType { input, hash_type });
+        }
+        let version = proof.argv_version().as_slice()[0];
+        if version > ARGV_VERSION_2 {
+            return Err(PreflightError::UnsupportedArgvVersion { input, version });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::fmt;
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    packed::{OutPoint, Script},
+    prelude::*,
+};
+use registry_common::{
+    hash::calc_script_hash,
+    node::{
+        decode_lock_args, decode_registry_args, Key, LayoutError, Lookup, Node, KEY_MAX, KEY_MIN,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::RegistryCell,
+    error::{hex, Error},
+};
+
+/// A live cell in a JSON dump, the fields use the JSON types of the CKB RPC
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JsonCell {
+    pub out_point: json_types::OutPoint,
+    pub output: json_types::CellOutput,
+    pub data: json_types::JsonBytes,
+}
+
+impl From<JsonCell> for RegistryCell {
+    fn from(cell: JsonCell) -> Self {
+        RegistryCell {
+            out_point: cell.out_point.into(),
+            output: cell.output.into(),
+            data: cell.data.into_bytes(),
+        }
+    }
+}
+
+impl From<&RegistryCell> for JsonCell {
+    fn from(cell: &RegistryCell) -> Self {
+        JsonCell {
+            out_point: cell.out_point.clone().into(),
+            output: cell.output.clone().into(),
+            data: json_types::JsonBytes::from_bytes(cell.data.clone()),
+        }
+    }
+}
+
+/// An inconsistency of the linked list rebuilt from a cell set, the nodes of a valid registry partition the
+/// whole key space [KEY_MIN, KEY_MAX)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// the registry cell can't be decoded
+    InvalidLayout(OutPoint, LayoutError),
+    /// the keys [start, end) are not covered by any node
+    Gap { start: Key, end: Key },
+    /// the keys [start, end) are covered by more than one node
+    Overlap { start: Key, end: Key },
+    /// more than one node starts at the key
+    Duplicate(Key),
+    /// the node covers no keys, its end is not after its start
+    InvalidRange { start: Key, end: Key },
+    /// the registry cell is not locked by the lock-wrapper in the type args of the registry
+    InvalidLock(OutPoint),
+    /// the lock args of the registry cell name the registry of the hash instead of this one
+    ForeignRegistry(OutPoint, Key),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::InvalidLayout(out_point, err) => {
+                let index: u32 = out_point.index().unpack();
+                write!(
+                    f,
+                    "cell {}:{} has an invalid layout: {:?}",
+                    hex(&out_point.tx_hash().unpack()),
+                    index,
+                    err
+                )
+            }
+            Issue::Gap { start, end } => {
+                write!(f, "keys [{}, {}) are not covered", hex(start), hex(end))
+            }
+            Issue::Overlap { start, end } => write!(
+                f,
+                "keys [{}, {}) are covered by more than one node",
+                hex(start),
+                hex(end)
+            ),
+            Issue::Duplicate(key) => write!(f, "more than one node starts at {}", hex(key)),
+            Issue::InvalidRange { start, end } => {
+                write!(f, "node [{}, {}) covers no keys", hex(start), hex(end))
+            }
+            Issue::InvalidLock(out_point) => {
+                let index: u32 = out_point.index().unpack();
+                write!(
+                    f,
+                    "cell {}:{} is not locked by the lock-wrapper of the registry",
+                    hex(&out_point.tx_hash().unpack()),
+                    index
+                )
+            }
+            Issue::ForeignRegistry(out_point, registry_hash) => {
+                let index: u32 = out_point.index().unpack();
+                write!(
+                    f,
+                    "cell {}:{} belongs to the registry {}",
+                    hex(&out_point.tx_hash().unpack()),
+                    index,
+                    hex(registry_hash)
+                )
+            }
+        }
+    }
+}
+
+/// The linked list of a registry rebuilt from a set of live cells, the nodes are ordered by start key
+#[derive(Clone, Debug, Default)]
+pub struct RegistryState {
+    nodes: Vec<(RegistryCell, Node)>,
+    invalid_cells: Vec<(OutPoint, LayoutError)>,
+    registry_hash: Key,
+    // the (code_hash, hash_type) of the lock-wrapper in the type args, none if they can't be decoded
+    lock_wrapper: Option<(Key, u8)>,
+}
+
+impl RegistryState {
+    /// Rebuilds the registry of the type script from the cells, the cells of the other type scripts are ignored
+    pub fn from_cells(
+        registry_type_script: &Script,
+        cells: impl IntoIterator<Item = RegistryCell>,
+    ) -> Self {
+        let mut state = RegistryState {
+            registry_hash: calc_script_hash(registry_type_script.as_slice()),
+            lock_wrapper: decode_registry_args(&registry_type_script.args().raw_data())
+                .ok()
+                .map(|(_, code_hash, hash_type)| (code_hash, hash_type)),
+            ..Default::default()
+        };
+        for cell in cells {
+            if cell.output.type_().to_opt().as_ref() != Some(registry_type_script) {
+                continue;
+            }
+            match cell.node() {
+                Ok(node) => state.nodes.push((cell, node)),
+                Err(Error::Layout(err)) => state.invalid_cells.push((cell.out_point, err)),
+                Err(err) => unreachable!("decode node: {}", err),
+            }
+        }
+        state.nodes.sort_by_key(|(_, node)| node.range.start);
+        state
+    }
+
+    /// Rebuilds the registry from a JSON dump of live cells, which is an array of `JsonCell`
+    pub fn from_json(registry_type_script: &Script, json: &str) -> Result<Self, Error> {
+        let cells: Vec<JsonCell> = serde_json::from_str(json)?;
+        Ok(Self::from_cells(
+            registry_type_script,
+            cells.into_iter().map(Into::into),
+        ))
+    }
+
+    /// Rebuilds the registry from the cells created in a ckb-testtool context
+    #[cfg(feature = "ckb-testtool")]
+    pub fn from_context(
+        registry_type_script: &Script,
+        context: &ckb_testtool::context::Context,
+    ) -> Self {
+        let cells = context
+            .cells
+            .iter()
+            .map(|(out_point, (output, data))| RegistryCell {
+                out_point: out_point.clone(),
+                output: output.clone(),
+                data: data.clone(),
+            });
+        Self::from_cells(registry_type_script, cells)
+    }
+
+    /// Dumps the registry cells as JSON, which can be loaded by `from_json`
+    pub fn to_json(&self) -> Result<String, Error> {
+        let cells: Vec<JsonCell> = self.cells().map(Into::into).collect();
+        Ok(serde_json::to_string_pretty(&cells)?)
+    }
+
+    /// The registry cells ordered by start key, the cells with invalid layouts are excluded
+    pub fn cells(&self) -> impl Iterator<Item = &RegistryCell> {
+        self.nodes.iter().map(|(cell, _)| cell)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().map(|(_, node)| node)
+    }
+
+    /// Checks the linked list, the cells with invalid layouts are reported first, then the issues of the nodes
+    /// ordered by key. The nodes covering no keys are left out of the coverage checks
+    pub fn issues(&self) -> Vec<Issue> {
+        let mut issues: Vec<Issue> = self
+            .invalid_cells
+            .iter()
+            .map(|(out_point, err)| Issue::InvalidLayout(out_point.clone(), *err))
+            .collect();
+
+        // the end of the keys covered so far
+        let mut cursor = KEY_MIN;
+        let mut last_start = None;
+        for (cell, node) in &self.nodes {
+            issues.extend(self.cell_issues(cell));
+            let range = node.range;
+            if range.is_empty() {
+                issues.push(Issue::InvalidRange {
+                    start: range.start,
+                    end: range.end,
+                });
+                continue;
+            }
+            if last_start == Some(range.start) {
+                issues.push(Issue::Duplicate(range.start));
+            } else if range.start > cursor {
+                issues.push(Issue::Gap {
+                    start: cursor,
+                    end: range.start,
+                });
+            } else if range.start < cursor {
+                issues.push(Issue::Overlap {
+                    start: range.start,
+                    end: range.end.min(cursor),
+                });
+            }
+            last_start = Some(range.start);
+            cursor = cursor.max(range.end);
+        }
+        if cursor < KEY_MAX {
+            issues.push(Issue::Gap {
+                start: cursor,
+                end: KEY_MAX,
+            });
+        }
+        issues
+    }
+
+    // the lock of a registry cell must be the lock-wrapper in the type args, with the args naming this registry
+    fn cell_issues(&self, cell: &RegistryCell) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let lock = cell.output.lock();
+        if let Some((code_hash, hash_type)) = self.lock_wrapper {
+            if lock.code_hash().as_slice() != code_hash
+                || lock.hash_type().as_slice()[0] != hash_type
+            {
+                issues.push(Issue::InvalidLock(cell.out_point.clone()));
+            }
+        }
+        // the lock args are decoded along with the node
+        if let Ok((registry_hash, _)) = decode_lock_args(&lock.args().raw_data()) {
+            if registry_hash != self.registry_hash {
+                issues.push(Issue::ForeignRegistry(
+                    cell.out_point.clone(),
+                    registry_hash,
+                ));
+            }
+        }
+        issues
+    }
+
+    /// Finds the node covering the wrapped script hash, which is the node `exec_wrapped_script` loads from the cell
+    /// deps on-chain, the cells with the issues of `cell_issues` are skipped like `find_registry_node` does. If the
+    /// nodes overlap, only the one with the greatest start not after the hash is considered
+    pub fn lookup(&self, wrapped_hash: &Key) -> Option<(&RegistryCell, Lookup)> {
+        let index = self
+            .nodes
+            .partition_point(|(_, node)| node.range.start <= *wrapped_hash);
+        let (cell, node) = self.nodes[..index]
+            .iter()
+            .rev()
+            .find(|(cell, _)| self.cell_issues(cell).is_empty())?;
+        match node.range.lookup(wrapped_hash) {
+            Lookup::Outside => None,
+            lookup => Some((cell, lookup)),
+        }
+    }
+}
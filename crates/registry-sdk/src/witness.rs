@@ -0,0 +1,21 @@
+use ckb_types::{
+    packed::{Byte, Script, WitnessArgs},
+    prelude::*,
+};
+use registry_common::{argv::ARGV_VERSION_LEGACY, schema};
+
+/// A proof of the wrapped script passing the witness at `witness_index` to it with the legacy argv
+pub fn wrapped_proof(wrapped_script: &Script, witness_index: u16) -> schema::WrappedProof {
+    schema::WrappedProof::new_builder()
+        .wrapped_script(schema::Script::new_unchecked(wrapped_script.as_bytes()))
+        .witness_index(witness_index.into())
+        .argv_version(Byte::new(ARGV_VERSION_LEGACY))
+        .build()
+}
+
+/// The wrapper witness is put in the lock field of `WitnessArgs`, the type fields are left to the type scripts
+pub fn wrapper_witness_args(wrapper_witness: &schema::WrapperWitness) -> WitnessArgs {
+    WitnessArgs::new_builder()
+        .lock(Some(wrapper_witness.as_bytes()).pack())
+        .build()
+}
@@ -0,0 +1,4 @@
+//! Native build of `demo-script-b`, loaded by ckb-testtool in place of the riscv binary when the
+//! tests run with the `native-simulator` feature.
+
+ckb_std::entry_simulator!(demo_script_b::program_entry);
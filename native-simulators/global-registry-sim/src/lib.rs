@@ -0,0 +1,4 @@
+//! Native build of `global-registry`, loaded by ckb-testtool in place of the riscv binary when the
+//! tests run with the `native-simulator` feature.
+
+ckb_std::entry_simulator!(global_registry::program_entry);
@@ -0,0 +1,4 @@
+//! Native build of `lock-wrapper`, loaded by ckb-testtool in place of the riscv binary when the
+//! tests run with the `native-simulator` feature.
+
+ckb_std::entry_simulator!(lock_wrapper::program_entry);
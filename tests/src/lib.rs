@@ -1,4 +1,5 @@
-use ckb_testtool::ckb_types::bytes::Bytes;
+use ckb_testtool::ckb_types::{bytes::Bytes, packed::OutPoint};
+use ckb_testtool::context::Context;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
@@ -62,4 +63,38 @@ impl Loader {
         path.push(name);
         fs::read(path).expect("binary").into()
     }
+
+    pub fn deploy(&self, context: &mut Context, name: &str) -> OutPoint {
+        context.deploy_cell(self.load_binary(name))
+    }
+
+    // run the scripts referencing the contract by data hash in its native build instead of the riscv binary.
+    // ckb-testtool ignores the exit codes of the native runs and the simulator loads the transaction once per
+    // process, so the native runs only step through a transaction already verified in the VM, see
+    // `test_native_simulator`
+    #[cfg(feature = "native-simulator")]
+    pub fn set_simulator(&self, context: &mut Context, name: &str) {
+        use ckb_testtool::ckb_types::packed::CellOutput;
+        let code_hash = CellOutput::calc_data_hash(&self.load_binary(name));
+        let path = native_simulator_path(name);
+        context.set_simulator(code_hash, path.to_str().expect("simulator path"));
+    }
+}
+
+// the dylib built by `cargo build -p <name>-sim`, in `CARGO_TARGET_DIR` if it is set (e.g. by cargo-llvm-cov) or
+// in the workspace target directory
+#[cfg(feature = "native-simulator")]
+fn native_simulator_path(name: &str) -> PathBuf {
+    let mut path = match env::var("CARGO_TARGET_DIR") {
+        Ok(target_dir) => PathBuf::from(target_dir),
+        Err(_) => PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../target")),
+    };
+    path.push("debug");
+    path.push(format!(
+        "{}{}_sim{}",
+        env::consts::DLL_PREFIX,
+        name.replace('-', "_"),
+        env::consts::DLL_SUFFIX
+    ));
+    path
 }
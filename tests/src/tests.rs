@@ -1,273 +1,447 @@
 use super::*;
 use ckb_testtool::builtin::ALWAYS_SUCCESS;
-use ckb_testtool::ckb_types::{bytes::Bytes, core::TransactionBuilder, packed::*, prelude::*};
+use ckb_testtool::ckb_types::{
+    bytes::Bytes,
+    core::{
+        EpochNumberWithFraction, HeaderBuilder, HeaderView, ScriptHashType, TransactionBuilder,
+        TransactionView,
+    },
+    packed::*,
+    prelude::*,
+};
 use ckb_testtool::context::{random_hash, Context};
+use registry_cli::{ContractConfig, RegistryConfig};
+use registry_common::{
+    argv::{ArgvError, WrapperArgv, ARGV_VERSION_1, ARGV_VERSION_2, ARGV_VERSION_LEGACY},
+    hash::calc_init_hash,
+    node::{LayoutError, Lookup, Range, KEY_MAX, KEY_MIN, NO_OVERRIDE, PENDING_DELAY},
+    schema,
+    since::{absolute_since_after, is_absolute_since_reached},
+};
+use registry_sdk::{
+    decode_exit_code, wrapped_proof, CellRef, Contract, Issue, JsonCell, PreflightError,
+    RegistryCell, RegistryClient, RegistryState, ScriptCode,
+};
+use std::ffi::CString;
 
 const MAX_CYCLES: u64 = 10_000_000;
 
-#[test]
-fn test_init_global_registry() {
-    // deploy contract
-    let mut context = Context::default();
-    let contract_bin: Bytes = Loader::default().load_binary("global-registry");
-    let gr_out_point = context.deploy_cell(contract_bin);
-    let as_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+// error numbers, global-registry exits with 10..=39 and lock-wrapper with 40..=79
+const GR_INVALID_LINKED_LIST: i8 = 17;
+const GR_INVALID_INIT_OUTPUT_COUNT: i8 = 18;
+const GR_INVALID_SENTINEL_START: i8 = 19;
+const GR_INVALID_SENTINEL_END: i8 = 20;
+const GR_INVALID_SENTINEL_LOCK: i8 = 22;
+const GR_INVALID_INSERTED_CONFIG: i8 = 23;
+const GR_INVALID_NODE_LOCK: i8 = 24;
+const LW_INVALID_DATA_LENGTH: i8 = 45;
+const LW_INVALID_ARGS_LENGTH: i8 = 46;
+const LW_INVALID_CELL_DEP_REF: i8 = 47;
+const LW_INVALID_INPUT_COUNT: i8 = 48;
+const LW_INVALID_OUTPUT_LOCK_SCRIPT: i8 = 49;
+const LW_INVALID_WITNESS_FORMAT: i8 = 50;
+const LW_INVALID_WRAPPED_SCRIPT_HASH: i8 = 51;
+const LW_INVALID_NODE_CONFIG: i8 = 52;
+const LW_WRAPPED_SCRIPT_FAILED: i8 = 53;
+const LW_INVALID_WRAPPED_SCRIPT_HASH_TYPE: i8 = 54;
+const LW_INVALID_WRAPPER_WITNESS: i8 = 55;
+const LW_MISSING_WRAPPER_WITNESS: i8 = 56;
+const LW_INVALID_CONFIG_CHANGE: i8 = 57;
+const LW_GUARDIAN_DELAY_NOT_REACHED: i8 = 59;
+const LW_INVALID_MULTI_PROOF: i8 = 60;
+const LW_THRESHOLD_NOT_REACHED: i8 = 61;
+const LW_UNSUPPORTED_ARGV_VERSION: i8 = 63;
+const LW_UNSUPPORTED_VM_VERSION: i8 = 64;
+const LW_INVALID_REQUEST_SINCE: i8 = 65;
+const LW_INVALID_RECOVERY: i8 = 66;
+const LW_MIXED_ARGV_VERSIONS: i8 = 67;
 
-    // prepare lock script
-    let lock_script = context
-        .build_script(&as_out_point, Bytes::new())
-        .expect("script");
+fn lock_wrapper_args(registry_hash: [u8; 32], start: [u8; 32]) -> Bytes {
+    schema::LockWrapperArgs::new_builder()
+        .registry_hash(registry_hash.into())
+        .start(start.into())
+        .build()
+        .as_bytes()
+}
 
-    // prepare cells
-    let input_out_point = context.create_cell(
-        CellOutput::new_builder()
-            .capacity(2000u64.pack())
-            .lock(lock_script.clone())
-            .build(),
-        Bytes::new(),
-    );
-    let input = CellInput::new_builder()
-        .previous_output(input_out_point)
-        .build();
+fn registry_node(end: [u8; 32], config: [u8; 32]) -> Bytes {
+    schema::RegistryNode::new_builder()
+        .end(end.into())
+        .config(config.into())
+        .build()
+        .as_bytes()
+}
+
+fn registry_node_with_config(end: [u8; 32], config: schema::NodeConfig) -> Bytes {
+    Bytes::from([end.as_slice(), config.as_slice()].concat())
+}
 
-    // prepare type script
-    let mut hash = [0; 32];
-    let mut blake2b = blake2b_rs::Blake2bBuilder::new(32)
-        .personal(b"ckb-default-hash")
+// a node config of the wrapped script hash with a pending change to another one, the since is zero when proposed,
+// and the absolute since of the activation once anchored
+fn pending_config(
+    wrapped_script_hash: [u8; 32],
+    pending_hash: [u8; 32],
+    since: u64,
+) -> schema::NodeConfig {
+    let pending = schema::PendingConfig::new_builder()
+        .wrapped_script_hash(pending_hash.into())
+        .since(since.into())
         .build();
-    blake2b.update(input.as_slice());
-    blake2b.update(&0u64.to_le_bytes());
-    blake2b.finalize(&mut hash);
+    schema::NodeConfig::new_builder()
+        .wrapped_script_hash(wrapped_script_hash.into())
+        .pending(
+            schema::PendingConfigOpt::new_builder()
+                .set(Some(pending))
+                .build(),
+        )
+        .build()
+}
 
-    let type_script = context
-        .build_script(&gr_out_point, Bytes::from(hash.to_vec()))
-        .expect("script");
+fn wrapper_witness(wrapped_script: &Script, witness_index: u16) -> Bytes {
+    wrapper_witness_args(wrapped_script, witness_index)
+        .build()
+        .as_bytes()
+}
 
-    // prepare outputs
-    let output_lock_script = context
-        .build_script(&as_out_point, Bytes::from(vec![0u8; 32]))
-        .expect("script");
+// the wrapper witness is put in the lock field of WitnessArgs
+fn wrapper_witness_args(wrapped_script: &Script, witness_index: u16) -> WitnessArgsBuilder {
+    let wrapper_witness = schema::WrapperWitness::new_builder()
+        .set(wrapped_proof(wrapped_script, witness_index))
+        .build();
+    WitnessArgs::new_builder().lock(Some(wrapper_witness.as_bytes()).pack())
+}
 
-    let outputs = vec![
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(output_lock_script)
-            .type_(ScriptOpt::new_builder().set(Some(type_script)).build())
-            .build(),
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(lock_script)
-            .build(),
-    ];
+// a wrapper witness of the threshold scripts
+fn multi_proof(proofs: schema::WrappedProofVec) -> schema::WrapperWitness {
+    let multi_proof = schema::MultiProof::new_builder().proofs(proofs).build();
+    schema::WrapperWitness::new_builder()
+        .set(multi_proof)
+        .build()
+}
 
-    let outputs_data = vec![Bytes::from(vec![255u8; 32]), Bytes::new()];
+// a wrapper witness of the guardians
+fn guardian_proof(proofs: schema::WrappedProofVec) -> schema::WrapperWitness {
+    let guardian_proof = schema::GuardianProof::new_builder().proofs(proofs).build();
+    schema::WrapperWitness::new_builder()
+        .set(guardian_proof)
+        .build()
+}
 
-    // build transaction
-    let tx = TransactionBuilder::default()
-        .input(input)
-        .outputs(outputs)
-        .outputs_data(outputs_data.pack())
+// the witnesses of a transaction with one input presenting the proofs of the given candidate scripts,
+// the wrapper witness is followed by the inner witnesses of the proofs
+fn multi_proof_witnesses(
+    scripts: &[(Script, Vec<u8>)],
+    candidates: &[usize],
+    wrapper_witness: fn(schema::WrappedProofVec) -> schema::WrapperWitness,
+) -> Vec<ckb_testtool::ckb_types::packed::Bytes> {
+    let proofs = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| wrapped_proof(&scripts[*candidate].0, i as u16 + 1))
+        .collect();
+    let wrapper_witness =
+        wrapper_witness(schema::WrappedProofVec::new_builder().set(proofs).build());
+    let witness_args = WitnessArgs::new_builder()
+        .lock(Some(wrapper_witness.as_bytes()).pack())
         .build();
-    let tx = context.complete_tx(tx);
 
-    // run
-    let cycles = context
-        .verify_tx(&tx, MAX_CYCLES)
-        .expect("pass verification");
-    println!("consume cycles: {}", cycles);
+    let mut witnesses = vec![witness_args.as_bytes().pack()];
+    witnesses.extend(
+        candidates
+            .iter()
+            .map(|candidate| scripts[*candidate].1.pack()),
+    );
+    witnesses
 }
 
-#[test]
-fn test_update_global_registry() {
-    // deploy contract
-    let mut context = Context::default();
-    let contract_bin: Bytes = Loader::default().load_binary("global-registry");
-    let gr_out_point = context.deploy_cell(contract_bin);
-    let as_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+fn script_hash(script: &Script) -> [u8; 32] {
+    script.calc_script_hash().as_slice().try_into().unwrap()
+}
 
-    // prepare lock script and type script
-    let lock_script = context
-        .build_script(&as_out_point, Bytes::from(vec![0u8; 32]))
-        .expect("script");
+// the deployed contract referenced by data hash
+fn script_code(context: &Context, out_point: &OutPoint, hash_type: ScriptHashType) -> ScriptCode {
+    let (_, data) = context.get_cell(out_point).expect("cell");
+    ScriptCode::new(
+        CellOutput::calc_data_hash(&data),
+        hash_type,
+        CellDep::new_builder().out_point(out_point.clone()).build(),
+    )
+}
 
-    let type_script = ScriptOpt::new_builder()
-        .set(Some(
-            context
-                .build_script(&gr_out_point, random_hash().as_bytes())
-                .expect("script"),
-        ))
-        .build();
+// deploy global-registry and lock-wrapper, lock-wrapper is referenced by the hash type
+fn deploy_registry(
+    context: &mut Context,
+    lw_hash_type: ScriptHashType,
+) -> (ScriptCode, ScriptCode) {
+    let loader = Loader::default();
+    let gr_out_point = loader.deploy(context, "global-registry");
+    let lw_out_point = loader.deploy(context, "lock-wrapper");
+    (
+        script_code(context, &gr_out_point, ScriptHashType::Data1),
+        script_code(context, &lw_out_point, lw_hash_type),
+    )
+}
 
-    // prepare cells
-    let input_out_point = context.create_cell(
-        CellOutput::new_builder()
-            .capacity(3000u64.pack())
-            .lock(lock_script.clone())
-            .type_(type_script.clone())
-            .build(),
-        Bytes::from(vec![255u8; 32]),
-    );
-    let input = CellInput::new_builder()
-        .previous_output(input_out_point)
-        .build();
+// create the outputs of a committed transaction in the context
+fn create_outputs(context: &mut Context, tx: &TransactionView) {
+    for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+        context.create_cell_with_out_point(OutPoint::new(tx.hash(), index as u32), output, data);
+    }
+}
 
-    // prepare outputs
-    let output_lock_script_1 = context
-        .build_script(&as_out_point, Bytes::from(vec![0u8; 32]))
-        .expect("script");
+// create the outputs of a committed transaction in the context, and track its registry cells in the client
+fn commit_tx(context: &mut Context, client: &mut RegistryClient, tx: &TransactionView) {
+    create_outputs(context, tx);
+    client.apply_tx(tx);
+}
 
-    let output_lock_script_2 = context
-        .build_script(&as_out_point, Bytes::from(vec![100u8; 32]))
-        .expect("script");
+// the message is written by a script with the debug syscall, the context must capture the debug output
+fn assert_debug_message(context: &Context, message: &str) {
+    let messages: Vec<String> = context
+        .captured_messages()
+        .into_iter()
+        .map(|captured| captured.message)
+        .collect();
+    assert!(
+        messages.iter().any(|captured| captured == message),
+        "{:?} is not in the debug output {:?}",
+        message,
+        messages
+    );
+}
 
-    let output_lock_script_3 = context
-        .build_script(&as_out_point, Bytes::from(vec![200u8; 32]))
-        .expect("script");
+// the contracts and the registry client shared by the lock-wrapper tests
+struct LockWrapperContext {
+    context: Context,
+    client: RegistryClient,
+    dsa_out_point: OutPoint,
+    dsb_out_point: OutPoint,
+    lw_out_point: OutPoint,
+}
 
-    let outputs = vec![
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(output_lock_script_1)
-            .type_(type_script.clone())
-            .build(),
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(output_lock_script_2)
-            .type_(type_script.clone())
-            .build(),
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(output_lock_script_3)
-            .type_(type_script.clone())
-            .build(),
-    ];
+impl LockWrapperContext {
+    fn new() -> Self {
+        Self::with_hash_type(ScriptHashType::Data1)
+    }
 
-    let outputs_data = vec![
-        Bytes::from(vec![100u8; 32]),
-        Bytes::from(vec![200u8; 32]),
-        Bytes::from(vec![255u8; 32]),
-    ];
+    // lock-wrapper spawns the wrapped scripts when it runs on VM version 2 (hash type data2)
+    fn with_hash_type(lw_hash_type: ScriptHashType) -> Self {
+        let mut context = Context::default();
+        let loader = Loader::default();
+        let (gr_code, lw_code) = deploy_registry(&mut context, lw_hash_type);
+        let dsa_out_point = loader.deploy(&mut context, "demo-script-a");
+        let dsb_out_point = loader.deploy(&mut context, "demo-script-b");
+        let lw_out_point = lw_code.cell_dep.out_point();
+        let client = RegistryClient::new(gr_code, lw_code, random_hash().unpack());
+        Self {
+            context,
+            client,
+            dsa_out_point,
+            dsb_out_point,
+            lw_out_point,
+        }
+    }
 
-    // build transaction
-    let tx = TransactionBuilder::default()
-        .input(input)
-        .outputs(outputs)
-        .outputs_data(outputs_data.pack())
-        .build();
-    let tx = context.complete_tx(tx);
+    fn wrapped_script(&mut self, out_point: &OutPoint, args: Bytes) -> Script {
+        self.context.build_script(out_point, args).expect("script")
+    }
 
-    // run
-    let cycles = context
-        .verify_tx(&tx, MAX_CYCLES)
-        .expect("pass verification");
-    println!("consume cycles: {}", cycles);
-}
+    fn lock_script(&self, start: [u8; 32]) -> Script {
+        self.client.lock_script(start)
+    }
 
-#[test]
-fn test_lock_wrapper_load_without_config() {
-    // deploy contract
-    let mut context = Context::default();
-    let gr_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("global-registry");
-        context.deploy_cell(contract_bin)
-    };
-    let dsa_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("demo-script-a");
-        context.deploy_cell(contract_bin)
-    };
-    let lw_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("lock-wrapper");
-        context.deploy_cell(contract_bin)
-    };
+    // the cell deps of the demo scripts, which are not referenced by the cells so `complete_tx` doesn't add them
+    fn demo_cell_deps(&self) -> [CellDep; 2] {
+        [&self.dsa_out_point, &self.dsb_out_point]
+            .map(|out_point| CellDep::new_builder().out_point(out_point.clone()).build())
+    }
 
-    // prepare lock script and type script
-    let wrapped_script = context
-        .build_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()))
-        .expect("script");
-    let wrapped_script_hash: [u8; 32] = wrapped_script
-        .calc_script_hash()
-        .as_slice()
-        .try_into()
-        .unwrap();
-
-    let gr_type_script = context
-        .build_script(&gr_out_point, random_hash().as_bytes())
-        .expect("script");
-    let gr_type_script_hash: [u8; 32] = gr_type_script
-        .calc_script_hash()
-        .as_slice()
-        .try_into()
-        .unwrap();
-
-    let lock_script_1 = context
-        .build_script(
-            &lw_out_point,
-            Bytes::from([gr_type_script_hash, [0u8; 32]].concat()),
-        )
-        .expect("script");
+    // create a registry cell covering [start, end), the end and the config value are stored in the data,
+    // the cell is tracked by the client
+    fn registry_cell(&mut self, start: [u8; 32], data: Bytes) -> RegistryCell {
+        let output = self.registry_output(self.lock_script(start));
+        let out_point = self.context.create_cell(output.clone(), data.clone());
+        let cell = RegistryCell {
+            out_point,
+            output,
+            data,
+        };
+        self.client.add_cell(cell.clone());
+        cell
+    }
 
-    let lock_script_2 = context
-        .build_script(
-            &lw_out_point,
-            Bytes::from([gr_type_script_hash, wrapped_script_hash].concat()),
-        )
-        .expect("script");
+    fn registry_cell_dep(&mut self, start: [u8; 32], data: Bytes) -> CellDep {
+        self.registry_cell(start, data).cell_dep()
+    }
 
-    let type_script = ScriptOpt::new_builder().set(Some(gr_type_script)).build();
+    // create a registry cell covering [start, end) to be updated in the transaction
+    fn registry_input(&mut self, start: [u8; 32], data: Bytes) -> CellInput {
+        self.registry_cell(start, data).input()
+    }
 
-    // prepare cell deps
-    let cell_dep_out_point = context.create_cell(
+    fn registry_output(&self, lock_script: Script) -> CellOutput {
         CellOutput::new_builder()
             .capacity(1000u64.pack())
-            .lock(lock_script_1.clone())
-            .type_(type_script.clone())
-            .build(),
-        Bytes::from([[255u8; 32], [0u8; 32]].concat()),
+            .lock(lock_script)
+            .type_(Some(self.client.registry_type_script().clone()).pack())
+            .build()
+    }
+
+    // demo scripts with their valid inner witnesses, used as the scripts of a multi proof
+    fn candidate_scripts(&mut self) -> [(Script, Vec<u8>); 4] {
+        let dsa_out_point = self.dsa_out_point.clone();
+        let dsb_out_point = self.dsb_out_point.clone();
+        [
+            (
+                &dsa_out_point,
+                (0..32).collect::<Vec<_>>(),
+                (0..32).collect(),
+            ),
+            (&dsb_out_point, (0..32).collect(), (0..32).rev().collect()),
+            (&dsa_out_point, vec![7u8; 32], vec![7u8; 32]),
+            (&dsb_out_point, vec![7u8; 32], vec![7u8; 32]),
+        ]
+        .map(|(out_point, args, inner_witness)| {
+            (
+                self.wrapped_script(out_point, Bytes::from(args)),
+                inner_witness,
+            )
+        })
+    }
+
+    fn input(&mut self, lock_script: Script) -> CellInput {
+        let out_point = self.context.create_cell(
+            CellOutput::new_builder()
+                .capacity(3000u64.pack())
+                .lock(lock_script)
+                .build(),
+            Bytes::new(),
+        );
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+}
+
+fn assert_script_error(err: ckb_testtool::ckb_error::Error, err_code: i8) {
+    let error_string = err.to_string();
+    assert!(
+        error_string.contains(format!("error code {} ", err_code).as_str()),
+        "error_string: {}, expected: {}",
+        error_string,
+        describe_exit_code(err_code)
     );
+}
 
-    let cell_dep = CellDep::new_builder().out_point(cell_dep_out_point).build();
+// the error of the contract the exit code decodes to, the codes of other scripts are kept as is
+fn describe_exit_code(code: i8) -> String {
+    match Contract::of_exit_code(code) {
+        Some(contract) => decode_exit_code(contract, code).to_string(),
+        None => format!("exit code {}", code),
+    }
+}
+
+// the cells of the inputs and the cell deps of the transaction
+fn resolved_cells(context: &Context, tx: &TransactionView) -> Vec<RegistryCell> {
+    tx.input_pts_iter()
+        .chain(tx.cell_deps_iter().map(|cell_dep| cell_dep.out_point()))
+        .map(|out_point| {
+            let (output, data) = context.get_cell(&out_point).expect("cell");
+            RegistryCell {
+                out_point,
+                output,
+                data,
+            }
+        })
+        .collect()
+}
+
+// the exit code of the failing script
+fn script_exit_code(err: &ckb_testtool::ckb_error::Error) -> Option<i8> {
+    let error_string = err.to_string();
+    let (_, code) = error_string.split_once("error code ")?;
+    code.split(' ').next()?.parse().ok()
+}
+
+// verify the transaction with the contracts and cross-check the pre-flight verifier of the client, it reports no
+// error if the transaction passes, otherwise its first error has the exit code of the failing script. The pre-flight
+// verifier doesn't run the wrapped scripts, nor the checks of the VM version and of the anchors which need the
+// headers, so it only reports no error for a transaction failing on those
+fn verify_with_preflight(
+    context: &Context,
+    client: &RegistryClient,
+    tx: &TransactionView,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let errors = client.preflight(tx, &resolved_cells(context, tx));
+    let result = context.verify_tx(tx, MAX_CYCLES);
+    match (&result, errors.first()) {
+        (Ok(_), _) => assert!(
+            errors.is_empty(),
+            "pre-flight errors of a valid transaction: {:?}",
+            errors
+        ),
+        (Err(err), Some(preflight_err)) => {
+            let error_info = preflight_err.error_info().expect("error info");
+            assert!(
+                err.to_string()
+                    .contains(format!("error code {} ", error_info.code).as_str()),
+                "error_string: {}, pre-flight error: {} ({})",
+                err,
+                preflight_err,
+                error_info
+            );
+        }
+        (Err(err), None) => {
+            let error_info = script_exit_code(err)
+                .and_then(|code| Contract::of_exit_code(code).map(|c| decode_exit_code(c, code)));
+            let skipped = match error_info {
+                Some(info) if info.contract == Contract::GlobalRegistry => false,
+                Some(info) if info.contract == Contract::LockWrapper => matches!(
+                    info.name,
+                    "WrappedScriptFailed"
+                        | "SpawnRequired"
+                        | "UnsupportedVmVersion"
+                        | "InvalidRequestSince"
+                ),
+                Some(_) => true,
+                // the VM of the script can't run it
+                None => err.to_string().contains("VM Internal Error"),
+            };
+            assert!(
+                skipped,
+                "no pre-flight error of a failing transaction: {}",
+                err
+            );
+        }
+    }
+    result
+}
 
-    // prepare inputs
+// an input locked by the always success script to pay for the registry transactions
+fn always_success_input(context: &mut Context) -> CellInput {
+    let as_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&as_out_point, Bytes::new())
+        .expect("script");
     let input_out_point = context.create_cell(
         CellOutput::new_builder()
-            .capacity(3000u64.pack())
-            .lock(lock_script_2.clone())
+            .capacity(2000u64.pack())
+            .lock(lock_script)
             .build(),
         Bytes::new(),
     );
-    let input = CellInput::new_builder()
+    CellInput::new_builder()
         .previous_output(input_out_point)
-        .build();
-
-    // prepare outputs
-    let outputs = vec![CellOutput::new_builder()
-        .capacity(3000u64.pack())
-        .lock(lock_script_2.clone())
-        .build()];
-
-    let outputs_data = vec![Bytes::new()];
+        .build()
+}
 
-    // build transaction
-    let wrapped_script_witness_index = 1u16;
-    let wrapped_script_slice = wrapped_script.as_slice();
-    let witness = Bytes::from(
-        [
-            wrapped_script_witness_index.to_le_bytes().as_slice(),
-            wrapped_script_slice,
-        ]
-        .concat(),
-    );
-    let inner_witness = (0..32).collect::<Vec<_>>();
+#[test]
+fn test_init_global_registry() {
+    // deploy contract
+    let mut context = Context::default();
+    let (gr_code, lw_code) = deploy_registry(&mut context, ScriptHashType::Data1);
+    let input = always_success_input(&mut context);
 
-    let tx = TransactionBuilder::default()
-        .cell_dep(cell_dep)
-        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
-        .input(input)
-        .outputs(outputs)
-        .outputs_data(outputs_data.pack())
-        .witness(witness.pack())
-        .witness(inner_witness.pack())
-        .build();
+    // build transaction, the type args are derived from the first input
+    let (_client, tx) = RegistryClient::init(gr_code, lw_code, input).expect("init tx");
     let tx = context.complete_tx(tx);
 
     // run
@@ -278,107 +452,2833 @@ fn test_lock_wrapper_load_without_config() {
 }
 
 #[test]
-fn test_lock_wrapper_load_with_config() {
-    // deploy contract
+fn test_registry_client() {
     let mut context = Context::default();
-    let gr_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("global-registry");
-        context.deploy_cell(contract_bin)
-    };
-    let dsb_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("demo-script-b");
-        context.deploy_cell(contract_bin)
-    };
-    let lw_out_point = {
-        let contract_bin: Bytes = Loader::default().load_binary("lock-wrapper");
-        context.deploy_cell(contract_bin)
-    };
-
-    // prepare lock script and type script
-    let wrapped_script = context
-        .build_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()))
+    let (gr_code, lw_code) = deploy_registry(&mut context, ScriptHashType::Data1);
+    let dsa_out_point = Loader::default().deploy(&mut context, "demo-script-a");
+    let dsb_out_point = Loader::default().deploy(&mut context, "demo-script-b");
+    let script_a = context
+        .build_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()))
         .expect("script");
-    let wrapped_script_hash: [u8; 32] = wrapped_script
-        .calc_script_hash()
-        .as_slice()
-        .try_into()
-        .unwrap();
-    let gr_type_script = context
-        .build_script(&gr_out_point, random_hash().as_bytes())
+    let script_b = context
+        .build_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()))
         .expect("script");
-    let gr_type_script_hash: [u8; 32] = gr_type_script
-        .calc_script_hash()
-        .as_slice()
-        .try_into()
-        .unwrap();
+    let key = script_hash(&script_a);
+    let demo_cell_deps = [dsa_out_point, dsb_out_point]
+        .map(|out_point| CellDep::new_builder().out_point(out_point).build());
 
-    let lock_script = context
-        .build_script(
-            &lw_out_point,
-            Bytes::from([gr_type_script_hash, wrapped_script_hash].concat()),
-        )
-        .expect("script");
+    // init
+    let input = always_success_input(&mut context);
+    let (mut client, tx) = RegistryClient::init(gr_code, lw_code, input).expect("init tx");
+    let tx = context.complete_tx(tx);
+    verify_with_preflight(&context, &client, &tx).expect("pass verification");
+    commit_tx(&mut context, &mut client, &tx);
+    assert_eq!(
+        client.lookup(&key).map(|(_, lookup)| lookup),
+        Some(Lookup::Inside)
+    );
 
-    let type_script = ScriptOpt::new_builder().set(Some(gr_type_script)).build();
+    // insert the key of script a, the sentinel keeps its config so no proof is required
+    let tx = client.insert_key(key).expect("insert tx");
+    assert!(matches!(
+        client.insert_key(KEY_MIN),
+        Err(registry_sdk::Error::KeyExists(KEY_MIN))
+    ));
+    let tx = context.complete_tx(tx);
 
-    // prepare cell deps
-    let cell_dep_out_point = context.create_cell(
-        CellOutput::new_builder()
-            .capacity(1000u64.pack())
-            .lock(lock_script.clone())
-            .type_(type_script.clone())
+    // the key can't be inserted overridden by script b, which would take over the cells of script a without its
+    // consent
+    let (tail, tail_data) = client
+        .registry_cell(key, KEY_MAX, &script_hash(&script_b))
+        .expect("cell");
+    let takeover_tx = tx
+        .as_advanced_builder()
+        .set_outputs(vec![tx.output(0).unwrap(), tail])
+        .set_outputs_data(vec![tx.outputs_data().get(0).unwrap(), tail_data.pack()])
+        .build();
+    let err = verify_with_preflight(&context, &client, &takeover_tx).unwrap_err();
+    assert_script_error(err, GR_INVALID_INSERTED_CONFIG);
+
+    // the inserted node must be locked by the lock-wrapper of this registry, lock-wrapper skips the nodes locked
+    // otherwise, which would leave the keys they cover without an owner
+    let tail = tx.output(1).unwrap();
+    let lock = tail.lock();
+    let foreign_args = schema::LockWrapperArgs::new_builder()
+        .registry_hash([9u8; 32].into())
+        .start(key.into())
+        .build();
+    let foreign_locks = [
+        lock.clone()
+            .as_builder()
+            .code_hash(CellOutput::calc_data_hash(&ALWAYS_SUCCESS))
             .build(),
-        Bytes::from([[255u8; 32], wrapped_script_hash].concat()),
-    );
+        lock.clone()
+            .as_builder()
+            .hash_type(ScriptHashType::Data2.into())
+            .build(),
+        lock.as_builder()
+            .args(foreign_args.as_bytes().pack())
+            .build(),
+    ];
+    for foreign_lock in foreign_locks {
+        let foreign_tx = tx
+            .as_advanced_builder()
+            .set_outputs(vec![
+                tx.output(0).unwrap(),
+                tail.clone().as_builder().lock(foreign_lock).build(),
+            ])
+            .build();
+        let err = verify_with_preflight(&context, &client, &foreign_tx).unwrap_err();
+        assert_script_error(err, GR_INVALID_NODE_LOCK);
+    }
 
-    let cell_dep = CellDep::new_builder().out_point(cell_dep_out_point).build();
+    verify_with_preflight(&context, &client, &tx).expect("pass verification");
+    commit_tx(&mut context, &mut client, &tx);
+    assert_eq!(client.cells().len(), 2);
+    let (cell, lookup) = client.lookup(&key).expect("lookup");
+    assert_eq!(lookup, Lookup::Start);
+    assert_eq!(cell.node().unwrap().wrapped_script_hash(), key);
 
-    // prepare inputs
+    // the state rebuilt from the context has the spent sentinel too, the live cells tracked by the client form a
+    // valid linked list
+    let state = RegistryState::from_context(client.registry_type_script(), &context);
+    assert_eq!(state.cells().count(), 3);
+    let state = RegistryState::from_cells(client.registry_type_script(), client.cells().to_vec());
+    assert!(state.issues().is_empty());
+    let (state_cell, lookup) = state.lookup(&key).expect("lookup");
+    assert_eq!(lookup, Lookup::Start);
+    assert_eq!(state_cell.out_point, cell.out_point);
+
+    // the cells of the key are still unlocked by script a
+    let lock_script = client.lock_script(key);
     let input_out_point = context.create_cell(
         CellOutput::new_builder()
             .capacity(3000u64.pack())
-            .lock(lock_script.clone())
+            .lock(lock_script)
             .build(),
         Bytes::new(),
     );
     let input = CellInput::new_builder()
         .previous_output(input_out_point)
         .build();
+    let inner_witness: Bytes = (0..32).collect::<Vec<_>>().into();
+    assert!(matches!(
+        client.build_unlock_tx(
+            key,
+            &script_a,
+            vec![input.clone()],
+            0,
+            inner_witness.clone()
+        ),
+        Err(registry_sdk::Error::InvalidWitnessIndex(0))
+    ));
 
-    // prepare outputs
-    let outputs = vec![CellOutput::new_builder()
-        .capacity(3000u64.pack())
-        .lock(lock_script.clone())
-        .build()];
+    // the inner witness is placed after the input paying the fee, which is added later
+    let tx = client
+        .build_unlock_tx(key, &script_a, vec![input], 2, inner_witness)
+        .expect("unlock tx")
+        .as_advanced_builder()
+        .input(always_success_input(&mut context))
+        .cell_deps(demo_cell_deps)
+        .output(CellOutput::new_builder().capacity(5000u64.pack()).build())
+        .output_data(Bytes::new().pack())
+        .build();
+    let tx = context.complete_tx(tx);
+    let cycles = verify_with_preflight(&context, &client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
 
-    let outputs_data = vec![Bytes::new()];
+// builds the init tx of a new registry with the outputs replaced, and returns the verification error
+fn init_with_outputs(
+    outputs: impl FnOnce(&RegistryClient, &Script) -> Vec<(CellOutput, Bytes)>,
+) -> ckb_testtool::ckb_error::Error {
+    let mut context = Context::default();
+    let (gr_code, lw_code) = deploy_registry(&mut context, ScriptHashType::Data1);
+    let as_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let always_success = context
+        .build_script(&as_out_point, Bytes::new())
+        .expect("script");
+    let input = always_success_input(&mut context);
+    let (client, tx) = RegistryClient::init(gr_code, lw_code, input).expect("init tx");
+    let (outputs, outputs_data): (Vec<_>, Vec<_>) =
+        outputs(&client, &always_success).into_iter().unzip();
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs(outputs)
+        .set_outputs_data(outputs_data.iter().map(|data| data.pack()).collect())
+        .build();
+    let tx = context.complete_tx(tx);
+    context.verify_tx(&tx, MAX_CYCLES).unwrap_err()
+}
 
-    // build transaction
-    let wrapped_script_witness_index = 1u16;
-    let wrapped_script_slice = wrapped_script.as_slice();
-    let witness = Bytes::from(
-        [
-            wrapped_script_witness_index.to_le_bytes().as_slice(),
-            wrapped_script_slice,
+#[test]
+fn test_init_global_registry_with_multiple_cells() {
+    // two registry cells are created
+    let err = init_with_outputs(|client, _| {
+        vec![
+            client
+                .registry_cell(KEY_MIN, [100u8; 32], &NO_OVERRIDE)
+                .expect("cell"),
+            client
+                .registry_cell([100u8; 32], KEY_MAX, &NO_OVERRIDE)
+                .expect("cell"),
         ]
-        .concat(),
-    );
-    let inner_witness = (0..32).rev().collect::<Vec<_>>();
+    });
+    assert_script_error(err, GR_INVALID_INIT_OUTPUT_COUNT);
+}
 
-    let tx = TransactionBuilder::default()
-        .cell_dep(cell_dep)
-        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
-        .input(input)
-        .outputs(outputs)
-        .outputs_data(outputs_data.pack())
-        .witness(witness.pack())
-        .witness(inner_witness.pack())
-        .build();
-    let tx = context.complete_tx(tx);
+#[test]
+fn test_init_global_registry_with_invalid_sentinel() {
+    // the sentinel node does not cover the whole key space
+    let err = init_with_outputs(|client, always_success| {
+        vec![
+            client
+                .registry_cell(KEY_MIN, [200u8; 32], &NO_OVERRIDE)
+                .expect("cell"),
+            (
+                CellOutput::new_builder()
+                    .capacity(1000u64.pack())
+                    .lock(always_success.clone())
+                    .build(),
+                Bytes::new(),
+            ),
+        ]
+    });
+    assert_script_error(err, GR_INVALID_SENTINEL_END);
+}
 
-    // run
-    let cycles = context
+#[test]
+fn test_init_global_registry_with_invalid_sentinel_start() {
+    let err = init_with_outputs(|client, _| {
+        vec![client
+            .registry_cell([1u8; 32], KEY_MAX, &NO_OVERRIDE)
+            .expect("cell")]
+    });
+    assert_script_error(err, GR_INVALID_SENTINEL_START);
+}
+
+#[test]
+fn test_init_global_registry_with_invalid_sentinel_lock() {
+    // the sentinel is locked by another script with the lock args of lock-wrapper
+    let err = init_with_outputs(|client, always_success| {
+        let (output, data) = client
+            .registry_cell(KEY_MIN, KEY_MAX, &NO_OVERRIDE)
+            .expect("cell");
+        let lock = always_success
+            .clone()
+            .as_builder()
+            .args(lock_wrapper_args(client.registry_hash(), KEY_MIN).pack())
+            .build();
+        vec![(output.as_builder().lock(lock).build(), data)]
+    });
+    assert_script_error(err, GR_INVALID_SENTINEL_LOCK);
+
+    // the sentinel is locked by lock-wrapper, but the lock args name another registry
+    let err = init_with_outputs(|client, _| {
+        let (output, data) = client
+            .registry_cell(KEY_MIN, KEY_MAX, &NO_OVERRIDE)
+            .expect("cell");
+        let lock = client
+            .lock_wrapper()
+            .script(lock_wrapper_args([9u8; 32], KEY_MIN));
+        vec![(output.as_builder().lock(lock).build(), data)]
+    });
+    assert_script_error(err, GR_INVALID_SENTINEL_LOCK);
+}
+
+// the registry client of the global-registry tests, the registry cells are locked by the always success script with
+// the lock args of lock-wrapper, so only global-registry checks the transactions
+struct GlobalRegistryContext {
+    context: Context,
+    client: RegistryClient,
+}
+
+impl GlobalRegistryContext {
+    fn new() -> Self {
+        let mut context = Context::default();
+        let gr_out_point = Loader::default().deploy(&mut context, "global-registry");
+        let as_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+        let client = RegistryClient::new(
+            script_code(&context, &gr_out_point, ScriptHashType::Data1),
+            script_code(&context, &as_out_point, ScriptHashType::Data1),
+            random_hash().unpack(),
+        );
+        Self { context, client }
+    }
+
+    // create a registry cell covering [start, end) without override, the cell is tracked by the client
+    fn registry_cell(&mut self, start: [u8; 32], end: [u8; 32]) -> RegistryCell {
+        let (output, data) = self.output(start, end);
+        let output = output.as_builder().capacity(1000u64.pack()).build();
+        let out_point = self.context.create_cell(output.clone(), data.clone());
+        let cell = RegistryCell {
+            out_point,
+            output,
+            data,
+        };
+        self.client.add_cell(cell.clone());
+        cell
+    }
+
+    fn output(&self, start: [u8; 32], end: [u8; 32]) -> (CellOutput, Bytes) {
+        self.client
+            .registry_cell(start, end, &NO_OVERRIDE)
+            .expect("cell")
+    }
+
+    fn change_output(&mut self) -> (CellOutput, Bytes) {
+        let as_out_point = self.context.deploy_cell(ALWAYS_SUCCESS.clone());
+        let lock_script = self
+            .context
+            .build_script(&as_out_point, Bytes::new())
+            .expect("script");
+        (
+            CellOutput::new_builder()
+                .capacity(500u64.pack())
+                .lock(lock_script)
+                .build(),
+            Bytes::new(),
+        )
+    }
+
+    // a transaction of the registry inputs and outputs, whose order is kept
+    fn verify(
+        &mut self,
+        inputs: &[&RegistryCell],
+        outputs: Vec<(CellOutput, Bytes)>,
+    ) -> Result<u64, ckb_testtool::ckb_error::Error> {
+        let (outputs, outputs_data): (Vec<_>, Vec<_>) = outputs.into_iter().unzip();
+        let tx = TransactionBuilder::default()
+            .inputs(inputs.iter().map(|cell| cell.input()))
+            .outputs(outputs)
+            .outputs_data(outputs_data.pack())
+            .build();
+        let tx = self.context.complete_tx(tx);
+        self.context.verify_tx(&tx, MAX_CYCLES)
+    }
+}
+
+#[test]
+fn test_update_global_registry() {
+    let mut gr = GlobalRegistryContext::new();
+    let sentinel = gr.registry_cell(KEY_MIN, [255u8; 32]);
+
+    // the SDK splits the node at one key
+    let tx = gr.client.insert_key([100u8; 32]).expect("insert tx");
+    let tx = gr.context.complete_tx(tx);
+    let cycles = gr
+        .context
         .verify_tx(&tx, MAX_CYCLES)
         .expect("pass verification");
     println!("consume cycles: {}", cycles);
+
+    // the node can be split at several keys in one transaction, which the SDK doesn't build
+    let outputs = vec![
+        gr.output(KEY_MIN, [100u8; 32]),
+        gr.output([100u8; 32], [200u8; 32]),
+        gr.output([200u8; 32], [255u8; 32]),
+    ];
+    let cycles = gr.verify(&[&sentinel], outputs).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_merge_global_registry() {
+    let mut gr = GlobalRegistryContext::new();
+    let head = gr.registry_cell(KEY_MIN, [100u8; 32]);
+    let tail = gr.registry_cell([100u8; 32], [255u8; 32]);
+
+    // [0, 100) and [100, 255) are merged into [0, 255), the inputs are in key order and the capacity of the removed
+    // node is released to a change cell
+    let outputs = vec![gr.output(KEY_MIN, [255u8; 32]), gr.change_output()];
+    let cycles = gr
+        .verify(&[&head, &tail], outputs)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_update_global_registry_with_reordered_outputs() {
+    let mut gr = GlobalRegistryContext::new();
+    let sentinel = gr.registry_cell(KEY_MIN, [255u8; 32]);
+
+    // [0, 255) is split into [200, 255), [0, 100), [100, 200) with a change cell in between
+    let outputs = vec![
+        gr.output([200u8; 32], [255u8; 32]),
+        gr.change_output(),
+        gr.output(KEY_MIN, [100u8; 32]),
+        gr.output([100u8; 32], [200u8; 32]),
+    ];
+    let cycles = gr.verify(&[&sentinel], outputs).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_merge_global_registry_with_reordered_inputs() {
+    let mut gr = GlobalRegistryContext::new();
+    gr.registry_cell(KEY_MIN, [100u8; 32]);
+    gr.registry_cell([100u8; 32], [255u8; 32]);
+
+    // the SDK spends the removed node [100, 255) before its predecessor [0, 100)
+    let tx = gr.client.remove_key([100u8; 32]).expect("remove tx");
+    let tx = gr.context.complete_tx(tx);
+    let cycles = gr
+        .context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_merge_non_consecutive_global_registry() {
+    let mut gr = GlobalRegistryContext::new();
+    let head = gr.registry_cell(KEY_MIN, [100u8; 32]);
+    let tail = gr.registry_cell([200u8; 32], [255u8; 32]);
+
+    // [0, 100) and [200, 255) are not consecutive, [100, 200) is missing. The SDK refuses to merge them, so the
+    // transaction is built by hand
+    assert!(matches!(
+        gr.client.remove_key([200u8; 32]),
+        Err(registry_sdk::Error::NoPredecessor(_))
+    ));
+    let outputs = vec![gr.output(KEY_MIN, [255u8; 32])];
+    let err = gr.verify(&[&head, &tail], outputs).unwrap_err();
+    assert_script_error(err, GR_INVALID_LINKED_LIST);
+}
+
+// unlock a lock-wrapper cell of the key with the wrapped script through the registry client, the inner witness
+// is checked by the wrapped script
+fn unlock_key(
+    lw: &mut LockWrapperContext,
+    key: [u8; 32],
+    wrapped_script: &Script,
+    inner_witness: Vec<u8>,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let lock_script = lw.lock_script(key);
+    let input = lw.input(lock_script.clone());
+    let tx = lw
+        .client
+        .build_unlock_tx(key, wrapped_script, vec![input], 1, inner_witness.into())
+        .expect("unlock tx")
+        .as_advanced_builder()
+        .cell_deps(lw.demo_cell_deps())
+        .output(
+            CellOutput::new_builder()
+                .capacity(3000u64.pack())
+                .lock(lock_script)
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_load_without_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // the key is inside the sentinel node, it is delegated to the wrapped script itself
+    lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let cycles = unlock_key(
+        &mut lw,
+        script_hash(&wrapped_script),
+        &wrapped_script,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_with_type_witness() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let key = script_hash(&wrapped_script);
+    lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let input = lw.input(lw.lock_script(key));
+
+    // the type fields of WitnessArgs are reserved for the type scripts
+    let witness = wrapper_witness_args(&wrapped_script, 1)
+        .input_type(Some(Bytes::from(vec![1u8; 65])).pack())
+        .output_type(Some(Bytes::from(vec![2u8; 65])).pack())
+        .build()
+        .as_bytes();
+    let inner_witness = (0..32).collect::<Vec<_>>();
+
+    let tx = lw
+        .client
+        .build_unlock_tx(key, &wrapped_script, vec![input], 1, Bytes::new())
+        .expect("unlock tx")
+        .as_advanced_builder()
+        .cell_deps(lw.demo_cell_deps())
+        .output(
+            CellOutput::new_builder()
+                .capacity(3000u64.pack())
+                .lock(lw.lock_script(key))
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .set_witnesses(vec![witness.pack(), inner_witness.pack()])
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_load_with_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let key = script_hash(&wrapped_script);
+
+    // the node starts at the key and its config is the wrapped script hash
+    lw.registry_cell_dep(key, registry_node([255u8; 32], key));
+    let cycles = unlock_key(&mut lw, key, &wrapped_script, (0..32).rev().collect())
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_load_from_any_cell_dep() {
+    let mut lw = LockWrapperContext::new();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let key = script_hash(&wrapped_script);
+    lw.registry_cell_dep(key, registry_node([255u8; 32], key));
+
+    // another registry node [0x00..00, key) which does not cover the key
+    let other_cell_dep = lw.registry_cell_dep([0u8; 32], registry_node(key, [0u8; 32]));
+    let input = lw.input(lw.lock_script(key));
+
+    let tx = lw
+        .client
+        .build_unlock_tx(
+            key,
+            &wrapped_script,
+            vec![input],
+            1,
+            (0..32).rev().collect::<Vec<_>>().into(),
+        )
+        .expect("unlock tx");
+
+    // the registry node is not the first cell dep
+    let mut cell_deps = lw.demo_cell_deps().to_vec();
+    cell_deps.push(other_cell_dep);
+    cell_deps.extend(tx.cell_deps_iter());
+    let tx = tx
+        .as_advanced_builder()
+        .set_cell_deps(cell_deps)
+        .output(
+            CellOutput::new_builder()
+                .capacity(3000u64.pack())
+                .lock(lw.lock_script(key))
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// unlock the cells of script b (demo-script-b with args 0..32) with the given script, the first cell dep is a registry
+// cell of another registry overriding the key with script a, the node of the registry covering the key follows it
+fn unlock_with_foreign_node(
+    with_registry_node: bool,
+    by_script_a: bool,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let lw_out_point = lw.lw_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let key = script_hash(&script_b);
+
+    // the foreign node carries the registry type script, but its lock args name another registry
+    let foreign_lock_script = lw
+        .context
+        .build_script_with_hash_type(
+            &lw_out_point,
+            ScriptHashType::Data1,
+            lock_wrapper_args([9u8; 32], key),
+        )
+        .expect("script");
+    let foreign_out_point = lw.context.create_cell(
+        lw.registry_output(foreign_lock_script),
+        registry_node([255u8; 32], script_hash(&script_a)),
+    );
+    let mut cell_deps = vec![CellDep::new_builder().out_point(foreign_out_point).build()];
+    if with_registry_node {
+        cell_deps.push(lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32])));
+    }
+    cell_deps.extend(lw.demo_cell_deps());
+
+    let (wrapped_script, inner_witness): (_, Vec<u8>) = if by_script_a {
+        (&script_a, (0..32).collect())
+    } else {
+        (&script_b, (0..32).rev().collect())
+    };
+    let input = lw.input(lw.lock_script(key));
+    let tx = TransactionBuilder::default()
+        .cell_dep(lw.client.lock_wrapper().cell_dep.clone())
+        .cell_deps(cell_deps)
+        .input(input)
+        .output(
+            CellOutput::new_builder()
+                .capacity(3000u64.pack())
+                .lock(lw.lock_script(key))
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .witness(wrapper_witness(wrapped_script, 1).pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_skip_foreign_registry_node() {
+    // the key is inside the node of the registry and delegated to script b itself
+    let cycles = unlock_with_foreign_node(true, false).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // the override of the foreign node doesn't apply
+    let err = unlock_with_foreign_node(true, true).unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH);
+
+    // the foreign node alone doesn't cover the key
+    let err = unlock_with_foreign_node(false, true).unwrap_err();
+    assert_script_error(err, LW_INVALID_CELL_DEP_REF);
+}
+
+#[test]
+fn test_lock_wrapper_load_with_node_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let key = script_hash(&wrapped_script);
+
+    // the config value is a molecule encoded NodeConfig
+    let config = schema::NodeConfig::new_builder()
+        .wrapped_script_hash(key.into())
+        .app_data(b"opaque app data".as_slice().into())
+        .build();
+    lw.registry_cell_dep(key, registry_node_with_config([255u8; 32], config));
+    let cycles = unlock_key(&mut lw, key, &wrapped_script, (0..32).rev().collect())
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_per_input_proofs() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = lw.lock_script(script_hash(&wrapped_script));
+    let cell_dep = lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let inputs = vec![lw.input(lock_script.clone()), lw.input(lock_script.clone())];
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(6000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    // build transaction, each input presents its own proof pointing to its own inner witness
+    let inner_witness = (0..32).collect::<Vec<_>>();
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&wrapped_script, 2).pack())
+        .witness(wrapper_witness(&wrapped_script, 3).pack())
+        .witness(inner_witness.pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_spawn_per_input_proofs() {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = lw.lock_script(script_hash(&wrapped_script));
+    let cell_dep = lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let inputs = vec![lw.input(lock_script.clone()), lw.input(lock_script.clone())];
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(6000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    // build transaction, the wrapped script is spawned for each proof
+    let inner_witness = (0..32).collect::<Vec<_>>();
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&wrapped_script, 2).pack())
+        .witness(wrapper_witness(&wrapped_script, 3).pack())
+        .witness(inner_witness.pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_spawn_with_failed_wrapped_script() {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = lw.lock_script(script_hash(&wrapped_script));
+    let cell_dep = lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let inputs = vec![lw.input(lock_script.clone()), lw.input(lock_script.clone())];
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(6000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    // build transaction, the inner witness of the second proof is invalid, the exit code of the spawned
+    // wrapped script is captured by the wrapper
+    let inner_witness = (0..32).collect::<Vec<_>>();
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&wrapped_script, 2).pack())
+        .witness(wrapper_witness(&wrapped_script, 3).pack())
+        .witness(inner_witness.pack())
+        .witness(Bytes::from(vec![0u8; 32]).pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run, the witness of the failing proof is reported with the debug syscall
+    lw.context.set_capture_debug(true);
+    let err = verify_with_preflight(&lw.context, &lw.client, &tx).unwrap_err();
+    assert_script_error(err, LW_WRAPPED_SCRIPT_FAILED);
+    assert_debug_message(
+        &lw.context,
+        "lock-wrapper: the wrapped script failed on witness 3",
+    );
+}
+
+#[test]
+fn test_lock_wrapper_per_input_proofs_with_invalid_proof() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let other_script = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = lw.lock_script(script_hash(&wrapped_script));
+    let cell_dep = lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let inputs = vec![lw.input(lock_script.clone()), lw.input(lock_script.clone())];
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(6000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    // build transaction, the proof of the second input is not the wrapped script
+    let inner_witness = (0..32).collect::<Vec<_>>();
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&wrapped_script, 2).pack())
+        .witness(wrapper_witness(&other_script, 3).pack())
+        .witness(inner_witness.pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run, the group input of the invalid proof is reported with the debug syscall
+    lw.context.set_capture_debug(true);
+    let err = verify_with_preflight(&lw.context, &lw.client, &tx).unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH);
+    assert_debug_message(
+        &lw.context,
+        "lock-wrapper: invalid wrapped script hash of group input 1",
+    );
+}
+
+#[test]
+fn test_lock_wrapper_update_multiple_configs() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // script b is proposed as the config of [10, 20), and script a as the config of [20, 30)
+    let inputs = vec![
+        lw.registry_input(
+            [10u8; 32],
+            registry_node([20u8; 32], script_hash(&script_a)),
+        ),
+        lw.registry_input(
+            [20u8; 32],
+            registry_node([30u8; 32], script_hash(&script_b)),
+        ),
+    ];
+
+    // the registry outputs are placed in the reversed order of the inputs
+    let lock_script_1 = lw.lock_script([10u8; 32]);
+    let lock_script_2 = lw.lock_script([20u8; 32]);
+    let outputs = vec![
+        lw.registry_output(lock_script_2),
+        lw.registry_output(lock_script_1),
+    ];
+
+    let outputs_data = [
+        registry_node_with_config(
+            [30u8; 32],
+            pending_config(script_hash(&script_b), script_hash(&script_a), 0),
+        ),
+        registry_node_with_config(
+            [20u8; 32],
+            pending_config(script_hash(&script_a), script_hash(&script_b), 0),
+        ),
+    ];
+
+    // build transaction, each registry input is authorized by the wrapped script of its current config
+    let tx = TransactionBuilder::default()
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&script_a, 2).pack())
+        .witness(wrapper_witness(&script_b, 3).pack())
+        .witness((0..32).collect::<Vec<_>>().pack())
+        .witness((0..32).rev().collect::<Vec<_>>().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_update_config_with_other_inputs() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let start = script_hash(&script_a);
+    lw.registry_input(start, registry_node([255u8; 32], [0u8; 32]));
+
+    // the config of the node is carried so no proof is presented, a cell of the start key can't be spent along
+    let user_input = lw.input(lw.lock_script(start));
+    let tx = lw
+        .client
+        .update_config(start, &[0u8; 32])
+        .expect("update tx")
+        .as_advanced_builder()
+        .input(user_input)
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    let err = verify_with_preflight(&lw.context, &lw.client, &tx).unwrap_err();
+    assert_script_error(err, LW_INVALID_INPUT_COUNT);
+}
+
+#[test]
+fn test_lock_wrapper_update_config_without_successor() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let lw_out_point = lw.lw_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let input = lw.registry_input(
+        [10u8; 32],
+        registry_node([20u8; 32], script_hash(&script_a)),
+    );
+
+    // the registry output keeps the range but is locked by a different lock script
+    let other_lock_script = lw
+        .context
+        .build_script_with_hash_type(
+            &lw_out_point,
+            ScriptHashType::Data2,
+            lock_wrapper_args(lw.client.registry_hash(), [10u8; 32]),
+        )
+        .expect("script");
+    let outputs = vec![lw.registry_output(other_lock_script)];
+    let outputs_data = [registry_node([20u8; 32], script_hash(&script_a))];
+
+    // build transaction
+    let tx = TransactionBuilder::default()
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&script_a, 1).pack())
+        .witness((0..32).collect::<Vec<_>>().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let err = verify_with_preflight(&lw.context, &lw.client, &tx).unwrap_err();
+    assert_script_error(err, LW_INVALID_OUTPUT_LOCK_SCRIPT);
+}
+
+#[test]
+fn test_lock_wrapper_load_without_override() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let wrapped_script_hash = script_hash(&wrapped_script);
+    let lock_script = lw.lock_script(wrapped_script_hash);
+
+    // the node starts at the wrapped script hash, the zero config means the wrapped script in the lock args applies
+    let cell_dep = lw.registry_cell_dep(wrapped_script_hash, registry_node([255u8; 32], [0u8; 32]));
+    let input = lw.input(lock_script.clone());
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(3000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    // build transaction
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(&wrapped_script, 1).pack())
+        .witness((0..32).collect::<Vec<_>>().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// update the config payload of the node starting at the hash of script a from `config` to `new_config`,
+// the update is authorized by the given wrapped script, and the registry input has the given since
+fn update_config(
+    lw: &mut LockWrapperContext,
+    config: &[u8],
+    new_config: &[u8],
+    since: u64,
+    authorizer: &Script,
+    inner_witness: Vec<u8>,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let start = script_hash(&script_a);
+    let input = lw
+        .registry_input(
+            start,
+            Bytes::from([[255u8; 32].as_slice(), config].concat()),
+        )
+        .as_builder()
+        .since(since.pack())
+        .build();
+
+    let tx = lw
+        .client
+        .update_config(start, new_config)
+        .expect("update tx")
+        .as_advanced_builder()
+        .cell_deps(lw.demo_cell_deps())
+        .set_inputs(vec![input])
+        .witness(wrapper_witness(authorizer, 1).pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+// merge the node [start, 255) with the config into its predecessor [0, start) without override, the removal is
+// authorized by the proof of the script
+fn remove_key(
+    lw: &mut LockWrapperContext,
+    start: [u8; 32],
+    config: [u8; 32],
+    authorizer: Option<(&Script, Vec<u8>)>,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    lw.registry_cell([0u8; 32], registry_node(start, [0u8; 32]));
+    lw.registry_cell(start, registry_node([255u8; 32], config));
+
+    let tx = lw.client.remove_key(start).expect("remove tx");
+    let tx = match authorizer {
+        // the removed node is the first input, the predecessor needs no witness
+        Some((authorizer, inner_witness)) => tx
+            .as_advanced_builder()
+            .cell_deps(lw.demo_cell_deps())
+            .witness(wrapper_witness(authorizer, 2).pack())
+            .witness(Bytes::new().pack())
+            .witness(inner_witness.pack())
+            .build(),
+        None => tx,
+    };
+    let tx = lw.context.complete_tx(tx);
+    let result = verify_with_preflight(&lw.context, &lw.client, &tx);
+    if result.is_ok() {
+        commit_tx(&mut lw.context, &mut lw.client, &tx);
+    }
+    result
+}
+
+#[test]
+fn test_lock_wrapper_remove_key() {
+    let mut lw = LockWrapperContext::new();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // the override of [100, 255) consents to the removal of its node
+    let cycles = remove_key(
+        &mut lw,
+        [100u8; 32],
+        script_hash(&script_b),
+        Some((&script_b, (0..32).rev().collect())),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // the predecessor covers the removed key, which is delegated to itself
+    let (cell, lookup) = lw.client.lookup(&[100u8; 32]).expect("lookup");
+    assert_eq!(lookup, Lookup::Inside);
+    assert_eq!(
+        cell.node().unwrap().range,
+        Range::new([0u8; 32], [255u8; 32])
+    );
+}
+
+#[test]
+fn test_lock_wrapper_remove_key_without_override() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // the node without override is owned by the script of its start key
+    let cycles = remove_key(
+        &mut lw,
+        script_hash(&script_a),
+        [0u8; 32],
+        Some((&script_a, (0..32).collect())),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_remove_key_without_consent() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // the owner of another node can't remove the override of [100, 255)
+    let err = remove_key(
+        &mut lw,
+        [100u8; 32],
+        script_hash(&script_b),
+        Some((&script_a, (0..32).collect())),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH);
+
+    // nor anyone without a proof
+    let mut lw = LockWrapperContext::new();
+    let err = remove_key(&mut lw, [100u8; 32], script_hash(&script_b), None).unwrap_err();
+    assert_script_error(err, LW_MISSING_WRAPPER_WITNESS);
+}
+
+#[test]
+fn test_remove_key_with_unanchored_predecessor() {
+    let mut lw = LockWrapperContext::new();
+    lw.registry_cell(
+        [0u8; 32],
+        registry_node_with_config([100u8; 32], pending_config([0u8; 32], [1u8; 32], 0)),
+    );
+    lw.registry_cell([100u8; 32], registry_node([255u8; 32], [0u8; 32]));
+
+    // the predecessor keeps its config, so its request must be anchored first
+    assert!(matches!(
+        lw.client.remove_key([100u8; 32]),
+        Err(registry_sdk::Error::UnanchoredRequest(key)) if key == [0u8; 32]
+    ));
+    assert!(matches!(
+        lw.client.remove_key([0u8; 32]),
+        Err(registry_sdk::Error::NoPredecessor(key)) if key == [0u8; 32]
+    ));
+}
+
+#[test]
+fn test_lock_wrapper_reset_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let pending = pending_config(script_hash(&script_b), [0u8; 32], 0);
+
+    // clearing the override by script b is proposed by script b
+    let cycles = update_config(
+        &mut lw,
+        &script_hash(&script_b),
+        pending.as_slice(),
+        0,
+        &script_b,
+        (0..32).rev().collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // the pending config can't be promoted before the cell holding it is alive for the delay
+    let err = update_config(
+        &mut lw,
+        pending.as_slice(),
+        &[0u8; 32],
+        PENDING_DELAY - 1,
+        &script_b,
+        (0..32).rev().collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_CONFIG_CHANGE);
+
+    // after the delay, script a in the lock args takes over the node and promotes the pending config
+    let cycles = update_config(
+        &mut lw,
+        pending.as_slice(),
+        &[0u8; 32],
+        PENDING_DELAY,
+        &script_a,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // an anchored pending config is promoted once the since of the input reaches the anchor
+    let anchored = pending_config(script_hash(&script_b), [0u8; 32], 1000);
+    let err = update_config(
+        &mut lw,
+        anchored.as_slice(),
+        &[0u8; 32],
+        999,
+        &script_a,
+        (0..32).collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_CONFIG_CHANGE);
+    let cycles = update_config(
+        &mut lw,
+        anchored.as_slice(),
+        &[0u8; 32],
+        1000,
+        &script_a,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_early_pending_activation() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // a stolen key can't propose a pending config which is activated in the next transaction
+    let err = update_config(
+        &mut lw,
+        &[0u8; 32],
+        pending_config([0u8; 32], script_hash(&script_b), 1).as_slice(),
+        0,
+        &script_a,
+        (0..32).collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_REQUEST_SINCE);
+
+    // nor promote it before the delay
+    let pending = pending_config([0u8; 32], script_hash(&script_b), 0);
+    let err = update_config(
+        &mut lw,
+        pending.as_slice(),
+        &script_hash(&script_b),
+        0x8000_0000_0000_0001,
+        &script_b,
+        (0..32).rev().collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_CONFIG_CHANGE);
+}
+
+// the block of the number and epoch which created the cell, its header is added to the context
+fn link_block(
+    context: &mut Context,
+    out_point: &OutPoint,
+    number: u64,
+    epoch: EpochNumberWithFraction,
+) -> HeaderView {
+    let header = HeaderBuilder::default()
+        .number(number.pack())
+        .epoch(epoch.full_value().pack())
+        .build();
+    context.insert_header(header.clone());
+    context.link_cell_with_block(out_point.clone(), header.hash(), 0);
+    header
+}
+
+#[test]
+fn test_lock_wrapper_anchor_pending_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let start = [10u8; 32];
+
+    // the pending config was proposed in epoch 100 1/2
+    let pending = pending_config([0u8; 32], script_hash(&script_b), 0);
+    let cell = lw.registry_cell(start, registry_node_with_config([255u8; 32], pending));
+    let epoch = EpochNumberWithFraction::new(100, 500, 1000);
+    let header = link_block(&mut lw.context, &cell.out_point, 1, epoch);
+    let anchor = absolute_since_after(PENDING_DELAY, 1, epoch.full_value(), 0).expect("anchor");
+    assert_eq!(
+        anchor,
+        0x2000_0000_0000_0000 | EpochNumberWithFraction::new(106, 500, 1000).full_value()
+    );
+
+    // the node can't be split before the pending config is anchored
+    assert!(matches!(
+        lw.client.insert_key([200u8; 32]),
+        Err(registry_sdk::Error::UnanchoredRequest(key)) if key == start
+    ));
+
+    // the anchor is the since the delay after the block of the cell, anyone can record it
+    let tx = lw
+        .client
+        .anchor_requests(start, &header)
+        .expect("anchor tx");
+    let tx = lw.context.complete_tx(tx);
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // an earlier anchor is rejected
+    let (outputs, outputs_data): (Vec<_>, Vec<_>) = tx.outputs_with_data_iter().unzip();
+    let early = registry_node_with_config(
+        [255u8; 32],
+        pending_config([0u8; 32], script_hash(&script_b), anchor - 1),
+    );
+    let early_tx = tx
+        .as_advanced_builder()
+        .set_outputs(outputs)
+        .set_outputs_data(vec![early.pack()])
+        .build();
+    let err = lw.context.verify_tx(&early_tx, MAX_CYCLES).unwrap_err();
+    assert_script_error(err, LW_INVALID_REQUEST_SINCE);
+    assert_eq!(outputs_data.len(), 1);
+
+    // the anchor needs the header of the registry cell
+    let no_header_tx = tx.as_advanced_builder().set_header_deps(vec![]).build();
+    assert!(lw.context.verify_tx(&no_header_tx, MAX_CYCLES).is_err());
+
+    // the anchored node is split, the split keeps the anchor and script b replaces the start key at it
+    commit_tx(&mut lw.context, &mut lw.client, &tx);
+    let tx = lw.client.insert_key([200u8; 32]).expect("insert tx");
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    commit_tx(&mut lw.context, &mut lw.client, &tx);
+    let (_, lookup) = lw.client.lookup(&start).expect("lookup");
+    assert_eq!(lookup, Lookup::Start);
+    let node = lw.client.lookup(&start).unwrap().0.node().unwrap();
+    assert_eq!(
+        node.pending_request(),
+        Some((script_hash(&script_b), anchor))
+    );
+    assert_eq!(node.active_wrapped_script_hash(&[anchor - 1], None), start);
+    assert_eq!(
+        node.active_wrapped_script_hash(&[anchor], None),
+        script_hash(&script_b)
+    );
+}
+
+#[test]
+fn test_lock_wrapper_set_config_without_override() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let pending = pending_config([0u8; 32], script_hash(&script_b), 0);
+
+    // the node has no override, proposing script b as the override is authorized by script a in the lock args
+    let cycles = update_config(
+        &mut lw,
+        &[0u8; 32],
+        pending.as_slice(),
+        0,
+        &script_a,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // script b can't authorize it before it becomes the override
+    let err = update_config(
+        &mut lw,
+        &[0u8; 32],
+        pending.as_slice(),
+        0,
+        &script_b,
+        (0..32).rev().collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH);
+}
+
+#[test]
+fn test_lock_wrapper_change_config_immediately() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+
+    // the override can only be changed through a pending config
+    let err = update_config(
+        &mut lw,
+        &[0u8; 32],
+        &script_hash(&script_b),
+        0,
+        &script_a,
+        (0..32).collect(),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_CONFIG_CHANGE);
+}
+
+#[test]
+fn test_lock_wrapper_cancel_pending_config() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let pending = pending_config([0u8; 32], script_hash(&script_b), 100);
+
+    // the pending config is cancelled by script a before its activation
+    let cycles = update_config(
+        &mut lw,
+        pending.as_slice(),
+        &[0u8; 32],
+        0,
+        &script_a,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// unlock a cell locked by the hash of script a, the node starting at it has a pending change to script b
+// activated at block 100, the input has the given since and the unlock is authorized by script a or script b
+fn unlock_with_pending_config(
+    since: u64,
+    by_script_b: bool,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let script_b = lw.wrapped_script(&dsb_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let (authorizer, inner_witness) = if by_script_b {
+        (&script_b, (0..32).rev().collect::<Vec<_>>())
+    } else {
+        (&script_a, (0..32).collect::<Vec<_>>())
+    };
+
+    let lock_script = lw.lock_script(script_hash(&script_a));
+    let cell_dep = lw.registry_cell_dep(
+        script_hash(&script_a),
+        registry_node_with_config(
+            [255u8; 32],
+            pending_config([0u8; 32], script_hash(&script_b), 100),
+        ),
+    );
+    let input = lw
+        .input(lock_script.clone())
+        .as_builder()
+        .since(since.pack())
+        .build();
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(3000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(wrapper_witness(authorizer, 1).pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_load_with_pending_config() {
+    // script a still applies before the activation
+    let cycles = unlock_with_pending_config(99, false).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    let err = unlock_with_pending_config(99, true).unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH);
+
+    // script b applies after the activation
+    let cycles = unlock_with_pending_config(100, true).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// a node config with the override guarded by the first 3 candidate scripts with threshold 2 and delay 10 blocks,
+// and the since of the recovery request if any
+fn guarded_config(
+    scripts: &[(Script, Vec<u8>)],
+    wrapped_script_hash: [u8; 32],
+    recovery: Option<u64>,
+) -> schema::NodeConfig {
+    let guardians = schema::GuardianSet::new_builder()
+        .guardian_hashes(
+            schema::Byte32Vec::new_builder()
+                .set(
+                    scripts[..3]
+                        .iter()
+                        .map(|(script, _)| script_hash(script).into())
+                        .collect(),
+                )
+                .build(),
+        )
+        .threshold(Byte::new(2))
+        .delay((0x8000_0000_0000_0000u64 | 10).into())
+        .build();
+    let recovery = recovery.map(|since| {
+        schema::RecoveryRequest::new_builder()
+            .since(since.into())
+            .build()
+    });
+    schema::NodeConfig::new_builder()
+        .wrapped_script_hash(wrapped_script_hash.into())
+        .guardians(
+            schema::GuardianSetOpt::new_builder()
+                .set(Some(guardians))
+                .build(),
+        )
+        .recovery(
+            schema::RecoveryRequestOpt::new_builder()
+                .set(recovery)
+                .build(),
+        )
+        .build()
+}
+
+// the override and the since of the recovery request passed to `guarded_config`
+type GuardedConfig = ([u8; 32], Option<u64>);
+
+// the guardians update the config of the node [10, 255) whose override is a lost script, the proofs of the given
+// candidates are presented, the output config is built from script b (demo-script-b with args 0..32)
+fn guardian_update(
+    input_config: GuardedConfig,
+    output_config: fn(&Script) -> GuardedConfig,
+    since: u64,
+    candidates: &[usize],
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let scripts = lw.candidate_scripts();
+
+    let (wrapped_script_hash, recovery) = input_config;
+    let input = lw
+        .registry_input(
+            [10u8; 32],
+            registry_node_with_config(
+                [255u8; 32],
+                guarded_config(&scripts, wrapped_script_hash, recovery),
+            ),
+        )
+        .as_builder()
+        .since(since.pack())
+        .build();
+    let lock_script = lw.lock_script([10u8; 32]);
+    let outputs = vec![lw.registry_output(lock_script)];
+    let (wrapped_script_hash, recovery) = output_config(&scripts[1].0);
+    let outputs_data = [registry_node_with_config(
+        [255u8; 32],
+        guarded_config(&scripts, wrapped_script_hash, recovery),
+    )];
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witnesses(multi_proof_witnesses(&scripts, candidates, guardian_proof))
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+// the guardians execute the recovery requested with the since, rotating the override to script b (demo-script-b with
+// args 0..32) and clearing the request
+fn recover_config(
+    recovery: u64,
+    since: u64,
+    candidates: &[usize],
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    guardian_update(
+        ([5u8; 32], Some(recovery)),
+        |script_b| (script_hash(script_b), None),
+        since,
+        candidates,
+    )
+}
+
+#[test]
+fn test_lock_wrapper_guardian_recovery() {
+    // the guardians request the recovery without a delay
+    let cycles = guardian_update(([5u8; 32], None), |_| ([5u8; 32], Some(0)), 0, &[0, 2])
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // the unanchored request is executed once the registry cell created by it is alive for the delay
+    let cycles = recover_config(0, 0x8000_0000_0000_0000 | 10, &[0, 2]).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // the anchored request is executed once the since of the input reaches the anchor
+    let cycles = recover_config(100, 100, &[0, 2]).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_guardian_recovery_before_delay() {
+    let err = recover_config(0, 0x8000_0000_0000_0000 | 9, &[0, 2]).unwrap_err();
+    assert_script_error(err, LW_GUARDIAN_DELAY_NOT_REACHED);
+
+    // the delay of an unanchored request is relative to the creation of the registry cell
+    let err = recover_config(0, 10, &[0, 2]).unwrap_err();
+    assert_script_error(err, LW_GUARDIAN_DELAY_NOT_REACHED);
+
+    // an anchored request is not activated by the age of the registry cell
+    let err = recover_config(100, 99, &[0, 2]).unwrap_err();
+    assert_script_error(err, LW_GUARDIAN_DELAY_NOT_REACHED);
+    let err = recover_config(100, 0x8000_0000_0000_0000 | 10, &[0, 2]).unwrap_err();
+    assert_script_error(err, LW_GUARDIAN_DELAY_NOT_REACHED);
+}
+
+#[test]
+fn test_lock_wrapper_guardian_recovery_without_request() {
+    // the guardians can't change the config without a request, however old the registry cell is
+    let err = guardian_update(
+        ([5u8; 32], None),
+        |script_b| (script_hash(script_b), None),
+        0x8000_0000_0000_0000 | 10,
+        &[0, 2],
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_RECOVERY);
+
+    // nor when requesting the recovery
+    let err = guardian_update(
+        ([5u8; 32], None),
+        |script_b| (script_hash(script_b), Some(0)),
+        0,
+        &[0, 2],
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_RECOVERY);
+
+    // the request starts the delay when it is made
+    let err = guardian_update(([5u8; 32], None), |_| ([5u8; 32], Some(1)), 0, &[0, 2]).unwrap_err();
+    assert_script_error(err, LW_INVALID_REQUEST_SINCE);
+
+    // the executed request is cleared
+    let err = guardian_update(
+        ([5u8; 32], Some(100)),
+        |script_b| (script_hash(script_b), Some(100)),
+        100,
+        &[0, 2],
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_RECOVERY);
+}
+
+#[test]
+fn test_lock_wrapper_guardian_recovery_below_threshold() {
+    let err = recover_config(0, 0x8000_0000_0000_0000 | 10, &[1]).unwrap_err();
+    assert_script_error(err, LW_THRESHOLD_NOT_REACHED);
+
+    let err = guardian_update(([5u8; 32], None), |_| ([5u8; 32], Some(0)), 0, &[1]).unwrap_err();
+    assert_script_error(err, LW_THRESHOLD_NOT_REACHED);
+}
+
+#[test]
+fn test_lock_wrapper_guardian_recovery_with_invalid_proofs() {
+    // the same guardian can't be counted twice
+    let err = recover_config(0, 0x8000_0000_0000_0000 | 10, &[0, 0]).unwrap_err();
+    assert_script_error(err, LW_INVALID_MULTI_PROOF);
+
+    // the proof of a script which is not a guardian
+    let err = recover_config(0, 0x8000_0000_0000_0000 | 10, &[0, 3]).unwrap_err();
+    assert_script_error(err, LW_INVALID_MULTI_PROOF);
+}
+
+#[test]
+fn test_lock_wrapper_cancel_guardian_recovery() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let script_a = lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let scripts = lw.candidate_scripts();
+
+    // the owner of the node cancels the recovery requested by the guardians
+    let cycles = update_config(
+        &mut lw,
+        guarded_config(&scripts, [0u8; 32], Some(100)).as_slice(),
+        guarded_config(&scripts, [0u8; 32], None).as_slice(),
+        0,
+        &script_a,
+        (0..32).collect(),
+    )
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_split_keeps_guardian_recovery() {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let scripts = lw.candidate_scripts();
+    let start = [10u8; 32];
+
+    // the guardians requested the recovery in block 50, the request is anchored at block 60
+    let cell = lw.registry_cell(
+        start,
+        registry_node_with_config([255u8; 32], guarded_config(&scripts, [5u8; 32], Some(0))),
+    );
+    let epoch = EpochNumberWithFraction::new(1, 0, 1000);
+    let header = link_block(&mut lw.context, &cell.out_point, 50, epoch);
+    let tx = lw
+        .client
+        .anchor_requests(start, &header)
+        .expect("anchor tx");
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    commit_tx(&mut lw.context, &mut lw.client, &tx);
+    let node = lw.client.lookup(&start).unwrap().0.node().unwrap();
+    assert_eq!(
+        node.recovery_request(),
+        Some((0x8000_0000_0000_0000 | 10, 60))
+    );
+
+    // the owner splits the node, the split can't restart the delay
+    let tx = lw.client.insert_key([100u8; 32]).expect("insert tx");
+    let tx = lw.context.complete_tx(tx);
+    let (outputs, mut outputs_data): (Vec<_>, Vec<_>) = tx.outputs_with_data_iter().unzip();
+    outputs_data[0] =
+        registry_node_with_config([100u8; 32], guarded_config(&scripts, [5u8; 32], Some(0)));
+    let reset_tx = tx
+        .as_advanced_builder()
+        .set_outputs(outputs)
+        .set_outputs_data(outputs_data.iter().map(|data| data.pack()).collect())
+        .build();
+    let err = lw.context.verify_tx(&reset_tx, MAX_CYCLES).unwrap_err();
+    assert_script_error(err, LW_INVALID_REQUEST_SINCE);
+    verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    commit_tx(&mut lw.context, &mut lw.client, &tx);
+
+    // the node created by the split is recovered at the anchor, its own age doesn't count
+    let cell = lw.client.lookup(&start).unwrap().0.clone();
+    let output = lw.registry_output(lw.lock_script(start));
+    let recover = |since: u64| {
+        TransactionBuilder::default()
+            .cell_dep(
+                CellDep::new_builder()
+                    .out_point(dsa_out_point.clone())
+                    .build(),
+            )
+            .cell_dep(
+                CellDep::new_builder()
+                    .out_point(dsb_out_point.clone())
+                    .build(),
+            )
+            .input(cell.input().as_builder().since(since.pack()).build())
+            .output(output.clone())
+            .output_data(
+                registry_node_with_config(
+                    [100u8; 32],
+                    guarded_config(&scripts, script_hash(&scripts[1].0), None),
+                )
+                .pack(),
+            )
+            .witnesses(multi_proof_witnesses(&scripts, &[0, 2], guardian_proof))
+            .build()
+    };
+    let tx = lw.context.complete_tx(recover(0x8000_0000_0000_0000 | 10));
+    let err = verify_with_preflight(&lw.context, &lw.client, &tx).unwrap_err();
+    assert_script_error(err, LW_GUARDIAN_DELAY_NOT_REACHED);
+    let tx = lw.context.complete_tx(recover(60));
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// a node config whose start key is owned by 2 of the first 3 candidate scripts
+fn threshold_config(scripts: &[(Script, Vec<u8>)], app_data: &[u8]) -> schema::NodeConfig {
+    let threshold_scripts = schema::ThresholdScripts::new_builder()
+        .wrapped_script_hashes(
+            schema::Byte32Vec::new_builder()
+                .set(
+                    scripts[..3]
+                        .iter()
+                        .map(|(script, _)| script_hash(script).into())
+                        .collect(),
+                )
+                .build(),
+        )
+        .threshold(Byte::new(2))
+        .build();
+    schema::NodeConfig::new_builder()
+        .app_data(app_data.into())
+        .threshold_scripts(
+            schema::ThresholdScriptsOpt::new_builder()
+                .set(Some(threshold_scripts))
+                .build(),
+        )
+        .build()
+}
+
+// unlock a cell locked by [10u8; 32], the node starting at it is owned by 2 of the first 3 candidate scripts,
+// the proofs of the given candidates are presented
+fn unlock_with_threshold_scripts(
+    candidates: &[usize],
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let scripts = lw.candidate_scripts();
+    let lock_script = lw.lock_script([10u8; 32]);
+    let cell_dep = lw.registry_cell_dep(
+        [10u8; 32],
+        registry_node_with_config([255u8; 32], threshold_config(&scripts, &[])),
+    );
+    let input = lw.input(lock_script.clone());
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(3000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witnesses(multi_proof_witnesses(&scripts, candidates, multi_proof))
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_threshold_scripts() {
+    let cycles = unlock_with_threshold_scripts(&[1, 2]).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    // more proofs than the threshold are accepted as long as all of them succeed
+    let cycles = unlock_with_threshold_scripts(&[0, 1, 2]).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_threshold_scripts_with_invalid_proofs() {
+    let err = unlock_with_threshold_scripts(&[2]).unwrap_err();
+    assert_script_error(err, LW_THRESHOLD_NOT_REACHED);
+
+    let err = unlock_with_threshold_scripts(&[1, 1]).unwrap_err();
+    assert_script_error(err, LW_INVALID_MULTI_PROOF);
+
+    let err = unlock_with_threshold_scripts(&[1, 3]).unwrap_err();
+    assert_script_error(err, LW_INVALID_MULTI_PROOF);
+}
+
+#[test]
+fn test_lock_wrapper_update_config_by_threshold_scripts() {
+    let mut lw = LockWrapperContext::with_hash_type(ScriptHashType::Data2);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let dsb_out_point = lw.dsb_out_point.clone();
+    let scripts = lw.candidate_scripts();
+
+    // the app data of the node is updated by 2 of the owners
+    let input = lw.registry_input(
+        [10u8; 32],
+        registry_node_with_config([255u8; 32], threshold_config(&scripts, &[])),
+    );
+    let lock_script = lw.lock_script([10u8; 32]);
+    let outputs = vec![lw.registry_output(lock_script)];
+    let outputs_data = [registry_node_with_config(
+        [255u8; 32],
+        threshold_config(&scripts, &[1, 2, 3]),
+    )];
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .cell_dep(CellDep::new_builder().out_point(dsb_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witnesses(multi_proof_witnesses(&scripts, &[0, 2], multi_proof))
+        .build();
+    let tx = lw.context.complete_tx(tx);
+
+    // run
+    let cycles = verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// unlock a lock-wrapper cell delegating to the wrapped script (demo-script-a with args 0..32) without config
+fn unlock_wrapped_script(
+    lw: &mut LockWrapperContext,
+    wrapped_script: &Script,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    unlock_key(
+        lw,
+        script_hash(wrapped_script),
+        wrapped_script,
+        (0..32).collect(),
+    )
+}
+
+fn unlock_with_wrapped_hash_type(
+    lw_hash_type: ScriptHashType,
+    wrapped_hash_type: ScriptHashType,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::with_hash_type(lw_hash_type);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script = lw
+        .context
+        .build_script_with_hash_type(
+            &dsa_out_point,
+            wrapped_hash_type,
+            Bytes::from((0..32).collect::<Vec<_>>()),
+        )
+        .expect("script");
+    unlock_wrapped_script(&mut lw, &wrapped_script)
+}
+
+#[test]
+fn test_lock_wrapper_wrapped_hash_type_data() {
+    let cycles = unlock_with_wrapped_hash_type(ScriptHashType::Data1, ScriptHashType::Data)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_wrapped_hash_type_type() {
+    let cycles = unlock_with_wrapped_hash_type(ScriptHashType::Data1, ScriptHashType::Type)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_wrapped_hash_type_data1() {
+    let cycles = unlock_with_wrapped_hash_type(ScriptHashType::Data1, ScriptHashType::Data1)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_wrapped_hash_type_data2() {
+    let cycles = unlock_with_wrapped_hash_type(ScriptHashType::Data2, ScriptHashType::Data2)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// lock-wrapper referenced by data runs on VM version 0 which can neither exec nor spawn the wrapped script, the
+// contract rejects it with UnsupportedVmVersion without calling the vm_version syscall. VM version 0 also mis-executes
+// the `jalr ra, offset(ra)` calls emitted by current compilers, so the binary may fault before reaching the check,
+// either way the lock is rejected
+#[test]
+fn test_lock_wrapper_hash_type_data() {
+    let err =
+        unlock_with_wrapped_hash_type(ScriptHashType::Data, ScriptHashType::Data1).unwrap_err();
+    let error_string = err.to_string();
+    assert!(
+        error_string.contains(format!("error code {} ", LW_UNSUPPORTED_VM_VERSION).as_str())
+            || error_string.contains("VM Internal Error"),
+        "error_string: {}",
+        error_string
+    );
+    assert!(
+        error_string.contains("Inputs[0].Lock"),
+        "error_string: {}",
+        error_string
+    );
+}
+
+#[test]
+fn test_lock_wrapper_wrapped_hash_type_unknown() {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script = lw
+        .wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()))
+        .as_builder()
+        .hash_type(Byte::new(3))
+        .build();
+    let err = unlock_wrapped_script(&mut lw, &wrapped_script).unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPED_SCRIPT_HASH_TYPE);
+}
+
+// unlock two lock-wrapper cells delegating to demo-script-a, each input presents its own proof with the given argv
+// version
+fn unlock_with_argv_version(
+    lw_hash_type: ScriptHashType,
+    argv_version: u8,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    unlock_with_argv_versions(lw_hash_type, [argv_version; 2])
+}
+
+// unlock two lock-wrapper cells, each presents its own proof with the argv version
+fn unlock_with_argv_versions(
+    lw_hash_type: ScriptHashType,
+    argv_versions: [u8; 2],
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::with_hash_type(lw_hash_type);
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = lw.lock_script(script_hash(&wrapped_script));
+    let cell_dep = lw.registry_cell_dep([0u8; 32], registry_node([255u8; 32], [0u8; 32]));
+    let inputs = vec![lw.input(lock_script.clone()), lw.input(lock_script.clone())];
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(6000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    let inner_witness = (0..32).collect::<Vec<_>>();
+    let witness = |witness_index: u16, argv_version: u8| {
+        let proof = wrapped_proof(&wrapped_script, witness_index)
+            .as_builder()
+            .argv_version(Byte::new(argv_version))
+            .build();
+        let wrapper_witness = schema::WrapperWitness::new_builder().set(proof).build();
+        WitnessArgs::new_builder()
+            .lock(Some(wrapper_witness.as_bytes()).pack())
+            .build()
+            .as_bytes()
+    };
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(witness(2, argv_versions[0]).pack())
+        .witness(witness(3, argv_versions[1]).pack())
+        .witness(inner_witness.pack())
+        .witness(inner_witness.pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_exec_with_argv_version_1() {
+    let cycles =
+        unlock_with_argv_version(ScriptHashType::Data1, ARGV_VERSION_1).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_spawn_with_argv_version_1() {
+    let cycles =
+        unlock_with_argv_version(ScriptHashType::Data2, ARGV_VERSION_1).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_exec_with_argv_version_2() {
+    let cycles =
+        unlock_with_argv_version(ScriptHashType::Data1, ARGV_VERSION_2).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_spawn_with_argv_version_2() {
+    let cycles =
+        unlock_with_argv_version(ScriptHashType::Data2, ARGV_VERSION_2).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_argv_version_cycles() {
+    for lw_hash_type in [ScriptHashType::Data1, ScriptHashType::Data2] {
+        // the legacy layout still verifies with both exec and spawn
+        let [legacy, hex, binary] =
+            [ARGV_VERSION_LEGACY, ARGV_VERSION_1, ARGV_VERSION_2].map(|argv_version| {
+                unlock_with_argv_version(lw_hash_type, argv_version).expect("pass verification")
+            });
+        println!(
+            "consume cycles with {:?}: legacy {}, hex {}, binary {}",
+            lw_hash_type, legacy, hex, binary
+        );
+        // the binary layout passes the same fields as hex in fewer cycles, the legacy layout passes none of them
+        assert!(
+            binary < hex,
+            "binary argv costs {} cycles, hex argv {}",
+            binary,
+            hex
+        );
+        assert!(
+            legacy < binary,
+            "legacy argv costs {} cycles, binary argv {}",
+            legacy,
+            binary
+        );
+    }
+}
+
+#[test]
+fn test_lock_wrapper_with_mixed_argv_versions() {
+    // the wrapped script runs once with exec, so the proofs can't ask for different layouts, and spawn rejects them
+    // the same way
+    for lw_hash_type in [ScriptHashType::Data1, ScriptHashType::Data2] {
+        let err =
+            unlock_with_argv_versions(lw_hash_type, [ARGV_VERSION_1, ARGV_VERSION_2]).unwrap_err();
+        assert_script_error(err, LW_MIXED_ARGV_VERSIONS);
+    }
+}
+
+#[test]
+fn test_lock_wrapper_with_unsupported_argv_version() {
+    let err = unlock_with_argv_version(ScriptHashType::Data1, 3).unwrap_err();
+    assert_script_error(err, LW_UNSUPPORTED_ARGV_VERSION);
+}
+
+// unlock a lock-wrapper cell with the given lock args, the registry node [0, 255) with the given data is referenced
+// in the cell deps, and the witness built from the wrapped script is put at the index of the input
+fn unlock_with_layout(
+    lock_args: Option<Bytes>,
+    node_data: Bytes,
+    witness: impl FnOnce(&Script) -> Bytes,
+) -> Result<u64, ckb_testtool::ckb_error::Error> {
+    let mut lw = LockWrapperContext::new();
+    let dsa_out_point = lw.dsa_out_point.clone();
+    let wrapped_script =
+        lw.wrapped_script(&dsa_out_point, Bytes::from((0..32).collect::<Vec<_>>()));
+    let lock_script = match lock_args {
+        Some(lock_args) => lw.client.lock_wrapper().script(lock_args),
+        None => lw.lock_script(script_hash(&wrapped_script)),
+    };
+    let cell_dep = lw.registry_cell_dep([0u8; 32], node_data);
+    let input = lw.input(lock_script.clone());
+
+    let outputs = vec![CellOutput::new_builder()
+        .capacity(3000u64.pack())
+        .lock(lock_script)
+        .build()];
+
+    let outputs_data = [Bytes::new()];
+
+    let tx = TransactionBuilder::default()
+        .cell_dep(cell_dep)
+        .cell_dep(CellDep::new_builder().out_point(dsa_out_point).build())
+        .input(input)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .witness(witness(&wrapped_script).pack())
+        .witness((0..32).collect::<Vec<_>>().pack())
+        .build();
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx)
+}
+
+#[test]
+fn test_lock_wrapper_with_valid_layout() {
+    let cycles = unlock_with_layout(None, registry_node([255u8; 32], [0u8; 32]), |script| {
+        wrapper_witness(script, 1)
+    })
+    .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_wrapper_with_invalid_args_length() {
+    let err = unlock_with_layout(
+        Some(Bytes::from(vec![0u8; 63])),
+        registry_node([255u8; 32], [0u8; 32]),
+        |script| wrapper_witness(script, 1),
+    )
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_ARGS_LENGTH);
+}
+
+#[test]
+fn test_lock_wrapper_with_invalid_data_length() {
+    let err = unlock_with_layout(None, Bytes::from(vec![255u8; 31]), |script| {
+        wrapper_witness(script, 1)
+    })
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_DATA_LENGTH);
+}
+
+#[test]
+fn test_lock_wrapper_with_invalid_node_config() {
+    // the config payload is neither a 32 bytes hash nor a NodeConfig
+    let node_data = Bytes::from([[255u8; 32].as_slice(), &[1u8; 33]].concat());
+    let err = unlock_with_layout(None, node_data, |script| wrapper_witness(script, 1)).unwrap_err();
+    assert_script_error(err, LW_INVALID_NODE_CONFIG);
+}
+
+#[test]
+fn test_lock_wrapper_with_invalid_witness_format() {
+    let err = unlock_with_layout(None, registry_node([255u8; 32], [0u8; 32]), |_| {
+        Bytes::from(vec![1u8; 3])
+    })
+    .unwrap_err();
+    assert_script_error(err, LW_INVALID_WITNESS_FORMAT);
+}
+
+#[test]
+fn test_lock_wrapper_with_invalid_wrapper_witness() {
+    let witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![1u8; 3])).pack())
+        .build()
+        .as_bytes();
+    let err =
+        unlock_with_layout(None, registry_node([255u8; 32], [0u8; 32]), |_| witness).unwrap_err();
+    assert_script_error(err, LW_INVALID_WRAPPER_WITNESS);
+}
+
+#[test]
+fn test_lock_wrapper_without_wrapper_witness() {
+    let witness = WitnessArgs::default().as_bytes();
+    let err =
+        unlock_with_layout(None, registry_node([255u8; 32], [0u8; 32]), |_| witness).unwrap_err();
+    assert_script_error(err, LW_MISSING_WRAPPER_WITNESS);
+}
+
+#[test]
+fn test_range_lookup() {
+    let range = Range::new([100u8; 32], [200u8; 32]);
+    assert_eq!(range.lookup(&[100u8; 32]), Lookup::Start);
+    assert_eq!(range.lookup(&[150u8; 32]), Lookup::Inside);
+    // the end of a range is the start of the next node, it is not covered
+    assert_eq!(range.lookup(&[200u8; 32]), Lookup::Outside);
+    assert_eq!(range.lookup(&[50u8; 32]), Lookup::Outside);
+}
+
+#[test]
+fn test_absolute_since_reached() {
+    const EPOCH: u64 = 0x2000_0000_0000_0000;
+    const TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+    const RELATIVE: u64 = 0x8000_0000_0000_0000;
+    let epoch = |number: u64, index: u64, length: u64| EPOCH | number | index << 24 | length << 40;
+
+    // block number and timestamp
+    assert!(is_absolute_since_reached(100, 100));
+    assert!(is_absolute_since_reached(101, 100));
+    assert!(!is_absolute_since_reached(99, 100));
+    assert!(!is_absolute_since_reached(0, 100));
+    assert!(is_absolute_since_reached(TIMESTAMP | 100, TIMESTAMP | 100));
+    assert!(!is_absolute_since_reached(TIMESTAMP | 99, TIMESTAMP | 100));
+
+    // epoch with fraction
+    assert!(is_absolute_since_reached(epoch(10, 1, 2), epoch(10, 2, 4)));
+    assert!(is_absolute_since_reached(epoch(10, 3, 4), epoch(10, 1, 2)));
+    assert!(!is_absolute_since_reached(epoch(10, 1, 4), epoch(10, 1, 2)));
+    assert!(is_absolute_since_reached(epoch(11, 0, 0), epoch(10, 1, 2)));
+
+    // the metric must match and relative since values are never reached
+    assert!(!is_absolute_since_reached(TIMESTAMP | 100, 100));
+    assert!(!is_absolute_since_reached(epoch(100, 0, 1), 100));
+    assert!(!is_absolute_since_reached(RELATIVE | 100, 100));
+    assert!(!is_absolute_since_reached(100, RELATIVE | 100));
+}
+
+#[test]
+fn test_absolute_since_after() {
+    const EPOCH: u64 = 0x2000_0000_0000_0000;
+    const TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+    const RELATIVE: u64 = 0x8000_0000_0000_0000;
+    let epoch = |number: u64, index: u64, length: u64| number | index << 24 | length << 40;
+    let header_epoch = epoch(10, 300, 1000);
+
+    // block number and timestamp in seconds
+    assert_eq!(
+        absolute_since_after(RELATIVE | 5, 100, header_epoch, 0),
+        Some(105)
+    );
+    assert_eq!(
+        absolute_since_after(RELATIVE | TIMESTAMP | 60, 100, header_epoch, 1_000_999),
+        Some(TIMESTAMP | 1060)
+    );
+
+    // the epoch fraction is rounded up to the length of the epoch of the block
+    assert_eq!(
+        absolute_since_after(RELATIVE | EPOCH | 6, 100, header_epoch, 0),
+        Some(EPOCH | epoch(16, 300, 1000))
+    );
+    assert_eq!(
+        absolute_since_after(RELATIVE | EPOCH | epoch(1, 1, 3), 100, header_epoch, 0),
+        Some(EPOCH | epoch(11, 634, 1000))
+    );
+    assert_eq!(
+        absolute_since_after(RELATIVE | EPOCH | epoch(0, 3, 4), 100, header_epoch, 0),
+        Some(EPOCH | epoch(11, 50, 1000))
+    );
+
+    // the delay must be relative and the result must fit in a since value
+    assert_eq!(absolute_since_after(5, 100, header_epoch, 0), None);
+    assert_eq!(
+        absolute_since_after(RELATIVE | 0x00ff_ffff_ffff_ffff, 1, header_epoch, 0),
+        None
+    );
+    assert_eq!(
+        absolute_since_after(RELATIVE | EPOCH | 1, 100, epoch(0xff_ffff, 0, 1), 0),
+        None
+    );
+}
+
+#[test]
+fn test_wrapper_argv() {
+    let argv = |argv: &[CString]| WrapperArgv::decode(argv.iter().map(|arg| arg.as_c_str()));
+
+    // the legacy layout carries no context
+    let legacy = WrapperArgv {
+        version: ARGV_VERSION_LEGACY,
+        args: vec![1, 2, 3],
+        witness_indexes: vec![1, 258],
+        ..Default::default()
+    };
+    let encoded = legacy.encode().unwrap();
+    assert_eq!(encoded[0].to_bytes(), b"010203");
+    assert_eq!(encoded[2].to_bytes(), b"0201");
+    assert_eq!(argv(&encoded), Ok(legacy));
+
+    let v1 = WrapperArgv {
+        version: ARGV_VERSION_1,
+        args: vec![1, 2, 3],
+        wrapper_script_hash: [7u8; 32],
+        group_inputs: vec![0, 2],
+        witness_indexes: vec![3],
+    };
+    let encoded = v1.encode().unwrap();
+    assert_eq!(encoded[0].to_bytes(), [ARGV_VERSION_1]);
+    assert_eq!(encoded[3].to_bytes(), b"0000000002000000");
+    assert_eq!(argv(&encoded), Ok(v1));
+
+    // the binary encoding escapes nul and the escape byte, and is much smaller than the hex encoding
+    let v2 = WrapperArgv {
+        version: ARGV_VERSION_2,
+        args: vec![0, 1, 2, 3],
+        wrapper_script_hash: [7u8; 32],
+        group_inputs: vec![0, 2],
+        witness_indexes: vec![3, 256],
+    };
+    let encoded = v2.encode().unwrap();
+    assert_eq!(encoded[0].to_bytes(), [ARGV_VERSION_2]);
+    assert_eq!(encoded[1].to_bytes(), [1u8, 1, 1, 2, 2, 3]);
+    assert_eq!(encoded[5].to_bytes(), [1u8, 1, 1, 2]);
+    assert_eq!(argv(&encoded), Ok(v2.clone()));
+    let size = |argv: &[CString]| argv.iter().map(|arg| arg.as_bytes().len()).sum::<usize>();
+    let v1 = WrapperArgv {
+        version: ARGV_VERSION_1,
+        ..v2
+    };
+    assert!(size(&encoded) * 3 / 2 < size(&v1.encode().unwrap()));
+    assert_eq!(
+        argv(&[
+            CString::new([ARGV_VERSION_2]).unwrap(),
+            CString::new([1u8, 3]).unwrap()
+        ]),
+        Err(ArgvError::InvalidEncoding)
+    );
+
+    // unknown versions, missing witness indexes and malformed hex are rejected
+    let unsupported = WrapperArgv {
+        version: 3,
+        ..Default::default()
+    };
+    assert_eq!(unsupported.encode(), Err(ArgvError::UnsupportedVersion));
+    let version_3 = [CString::new([3u8]).unwrap(), CString::new("00").unwrap()];
+    assert_eq!(argv(&version_3), Err(ArgvError::UnsupportedVersion));
+    assert_eq!(
+        argv(&[CString::new("0102").unwrap()]),
+        Err(ArgvError::InvalidLength)
+    );
+    assert_eq!(
+        argv(&[CString::new("010").unwrap(), CString::new("0100").unwrap()]),
+        Err(ArgvError::InvalidEncoding)
+    );
+}
+
+#[test]
+fn test_registry_state_with_invalid_cells() {
+    let mut lw = LockWrapperContext::new();
+    lw.registry_cell([0u8; 32], registry_node([100u8; 32], [0u8; 32]));
+    // nodes covering no keys are left out of the coverage checks
+    lw.registry_cell([150u8; 32], registry_node([120u8; 32], [0u8; 32]));
+    lw.registry_cell([160u8; 32], registry_node([160u8; 32], [0u8; 32]));
+
+    // a node locked by another script and a node of another registry, both cover their keys and are tracked by the
+    // client
+    let create_cell = |lw: &mut LockWrapperContext, lock_script: Script, end: [u8; 32]| {
+        let output = lw.registry_output(lock_script);
+        let data = registry_node(end, [0u8; 32]);
+        let out_point = lw.context.create_cell(output.clone(), data.clone());
+        lw.client.add_cell(RegistryCell {
+            out_point: out_point.clone(),
+            output,
+            data,
+        });
+        out_point
+    };
+    let other_lock = lw
+        .lock_script([100u8; 32])
+        .as_builder()
+        .code_hash([1u8; 32].pack())
+        .build();
+    let other_lock_out_point = create_cell(&mut lw, other_lock, [200u8; 32]);
+    let foreign_args = schema::LockWrapperArgs::new_builder()
+        .registry_hash([9u8; 32].into())
+        .start([200u8; 32].into())
+        .build();
+    let foreign_lock = lw.client.lock_wrapper().script(foreign_args.as_bytes());
+    let foreign_out_point = create_cell(&mut lw, foreign_lock, KEY_MAX);
+
+    let state = RegistryState::from_context(lw.client.registry_type_script(), &lw.context);
+    let issues = state.issues();
+    assert_eq!(
+        issues,
+        vec![
+            Issue::InvalidLock(other_lock_out_point),
+            Issue::InvalidRange {
+                start: [150u8; 32],
+                end: [120u8; 32]
+            },
+            Issue::InvalidRange {
+                start: [160u8; 32],
+                end: [160u8; 32]
+            },
+            Issue::ForeignRegistry(foreign_out_point, [9u8; 32]),
+        ]
+    );
+    for issue in issues {
+        println!("{}", issue);
+    }
+
+    // the lookups skip the nodes lock-wrapper skips in the cell deps, so the keys they cover are not found
+    for key in [[170u8; 32], [250u8; 32]] {
+        assert!(state.lookup(&key).is_none());
+        assert!(lw.client.lookup(&key).is_none());
+    }
+    assert_eq!(
+        lw.client.lookup(&[50u8; 32]).map(|(_, lookup)| lookup),
+        Some(Lookup::Inside)
+    );
+}
+
+#[test]
+fn test_registry_state() {
+    let mut lw = LockWrapperContext::new();
+    lw.registry_cell([0u8; 32], registry_node([100u8; 32], [0u8; 32]));
+    lw.registry_cell([100u8; 32], registry_node([200u8; 32], [0u8; 32]));
+    // a duplicate of the node [100, 200), a node overlapping [180, 200), and [220, 0xff..ff) is not covered
+    lw.registry_cell([100u8; 32], registry_node([150u8; 32], [0u8; 32]));
+    lw.registry_cell([180u8; 32], registry_node([220u8; 32], [0u8; 32]));
+    // a cell with an invalid layout
+    let invalid = lw.registry_cell([230u8; 32], Bytes::from(vec![0u8; 16]));
+
+    let state = RegistryState::from_context(lw.client.registry_type_script(), &lw.context);
+    assert_eq!(
+        state.issues(),
+        vec![
+            Issue::InvalidLayout(invalid.out_point, LayoutError::InvalidData),
+            Issue::Duplicate([100u8; 32]),
+            Issue::Overlap {
+                start: [180u8; 32],
+                end: [200u8; 32]
+            },
+            Issue::Gap {
+                start: [220u8; 32],
+                end: KEY_MAX
+            },
+        ]
+    );
+
+    assert_eq!(
+        state.lookup(&[0u8; 32]).map(|(_, lookup)| lookup),
+        Some(Lookup::Start)
+    );
+    assert_eq!(
+        state.lookup(&[50u8; 32]).map(|(_, lookup)| lookup),
+        Some(Lookup::Inside)
+    );
+    assert_eq!(state.lookup(&[225u8; 32]).map(|(_, lookup)| lookup), None);
+
+    // the state is rebuilt from its JSON dump, which only has the valid cells
+    let json = state.to_json().expect("json");
+    let loaded = RegistryState::from_json(lw.client.registry_type_script(), &json).expect("state");
+    assert_eq!(loaded.cells().count(), 4);
+    assert_eq!(loaded.issues(), state.issues()[1..]);
+    let (cell, lookup) = loaded.lookup(&[190u8; 32]).expect("lookup");
+    assert_eq!(lookup, Lookup::Inside);
+    assert_eq!(
+        cell.node().unwrap().range,
+        Range::new([180u8; 32], [220u8; 32])
+    );
+}
+
+// the registry cells created by a committed transaction, as they are dumped for the cli
+fn json_cells(tx: &TransactionView) -> Vec<JsonCell> {
+    tx.outputs_with_data_iter()
+        .enumerate()
+        .map(|(index, (output, data))| {
+            JsonCell::from(&RegistryCell {
+                out_point: OutPoint::new(tx.hash(), index as u32),
+                output,
+                data,
+            })
+        })
+        .collect()
+}
+
+fn contract_config(code: &ScriptCode) -> ContractConfig {
+    ContractConfig {
+        code_hash: code.code_hash.unpack(),
+        hash_type: code.hash_type.into(),
+        cell_dep: code.cell_dep.clone().into(),
+    }
+}
+
+#[test]
+fn test_registry_cli() {
+    let mut context = Context::default();
+    let (gr_code, lw_code) = deploy_registry(&mut context, ScriptHashType::Data1);
+    let contracts = RegistryConfig {
+        global_registry: contract_config(&gr_code),
+        lock_wrapper: contract_config(&lw_code),
+        init_hash: None,
+    };
+    let input = always_success_input(&mut context);
+
+    // the type args of the registry file are the ones checked by validate_init_hash
+    let (registry, tx) = registry_cli::init(&contracts, input.previous_output()).expect("init");
+    assert_eq!(
+        registry.init_hash.clone().map(|hash| hash.0),
+        Some(calc_init_hash(input.as_slice(), 0))
+    );
+    assert!(matches!(
+        registry_cli::init(&registry, input.previous_output()),
+        Err(registry_cli::Error::InitHashExists)
+    ));
+    let registry: RegistryConfig =
+        serde_json::from_str(&serde_json::to_string(&registry).unwrap()).expect("registry file");
+    let tx = context.complete_tx(Transaction::from(tx).into_view());
+    context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+    create_outputs(&mut context, &tx);
+    let cells = json_cells(&tx);
+    assert!(registry_cli::verify(&registry, &cells).unwrap().is_empty());
+
+    // insert a key into the sentinel
+    let key = [100u8; 32];
+    let tx = registry_cli::insert(&registry, &cells, key).expect("insert");
+    let tx = context.complete_tx(Transaction::from(tx).into_view());
+    context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+    let cells = json_cells(&tx);
+    assert!(registry_cli::verify(&registry, &cells).unwrap().is_empty());
+
+    let result = registry_cli::lookup(&registry, &cells, key, 0)
+        .unwrap()
+        .expect("lookup");
+    assert_eq!(
+        OutPoint::from(result.out_point),
+        OutPoint::new(tx.hash(), 1)
+    );
+    assert_eq!(result.wrapped_script_hash.map(|hash| hash.0), Some(key));
+    let result = registry_cli::lookup(&registry, &cells, [50u8; 32], 0)
+        .unwrap()
+        .expect("lookup");
+    assert_eq!(result.start.0, KEY_MIN);
+    assert_eq!(
+        result.wrapped_script_hash.map(|hash| hash.0),
+        Some([50u8; 32])
+    );
+
+    // the pending config takes over the key once the since of the unlocking inputs reaches its activation
+    let mut pending_cells = cells.clone();
+    pending_cells[1].data =
+        registry_node_with_config([255u8; 32], pending_config([200u8; 32], [210u8; 32], 1000))
+            .pack()
+            .into();
+    for (since, wrapped_script_hash) in [(999, [200u8; 32]), (1000, [210u8; 32])] {
+        let result = registry_cli::lookup(&registry, &pending_cells, key, since)
+            .unwrap()
+            .expect("lookup");
+        assert_eq!(
+            result.wrapped_script_hash.map(|hash| hash.0),
+            Some(wrapped_script_hash)
+        );
+    }
+
+    // the cells of a start key owned by threshold scripts are unlocked by the threshold scripts only
+    let threshold_scripts = schema::ThresholdScripts::new_builder()
+        .wrapped_script_hashes(
+            schema::Byte32Vec::new_builder()
+                .set(vec![[1u8; 32].into(), [2u8; 32].into()])
+                .build(),
+        )
+        .threshold(Byte::new(2))
+        .build();
+    let mut threshold_cells = cells.clone();
+    threshold_cells[1].data = registry_node_with_config(
+        [255u8; 32],
+        schema::NodeConfig::new_builder()
+            .threshold_scripts(
+                schema::ThresholdScriptsOpt::new_builder()
+                    .set(Some(threshold_scripts))
+                    .build(),
+            )
+            .build(),
+    )
+    .pack()
+    .into();
+    let result = registry_cli::lookup(&registry, &threshold_cells, key, 0)
+        .unwrap()
+        .expect("lookup");
+    assert!(result.wrapped_script_hash.is_none());
+    let threshold_scripts = result.threshold_scripts.expect("threshold scripts");
+    assert_eq!(threshold_scripts.threshold, 2);
+    assert_eq!(
+        threshold_scripts
+            .wrapped_script_hashes
+            .iter()
+            .map(|hash| hash.0)
+            .collect::<Vec<_>>(),
+        vec![[1u8; 32], [2u8; 32]]
+    );
+
+    // without the sentinel the keys before the inserted one are not covered
+    let issues = registry_cli::verify(&registry, &cells[1..]).unwrap();
+    assert_eq!(
+        issues,
+        vec![Issue::Gap {
+            start: KEY_MIN,
+            end: key
+        }]
+    );
+    assert!(registry_cli::lookup(&registry, &cells[1..], [50u8; 32], 0)
+        .unwrap()
+        .is_none());
+
+    // only hex digits are accepted, `from_str_radix` alone would take the sign
+    assert_eq!(
+        registry_cli::parse_hex("0x0aff").unwrap(),
+        Bytes::from(vec![0x0a, 0xff])
+    );
+    for hex in ["0x+a", "+a+b", "0x-1", "0xabc", "0xzz"] {
+        assert!(registry_cli::parse_hex(hex).is_err(), "{}", hex);
+    }
+}
+
+// change the outputs of a valid transaction so it fails, and return the errors of the pre-flight verifier
+fn preflight_after(
+    context: &Context,
+    client: &RegistryClient,
+    tx: &TransactionView,
+    change: impl FnOnce(Vec<CellOutput>, Vec<Bytes>) -> (Vec<CellOutput>, Vec<Bytes>),
+) -> Vec<PreflightError> {
+    let (outputs, outputs_data) = change(
+        tx.outputs().into_iter().collect(),
+        tx.outputs_data()
+            .into_iter()
+            .map(|data| data.raw_data())
+            .collect(),
+    );
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs(outputs)
+        .set_outputs_data(outputs_data.into_iter().map(|data| data.pack()).collect())
+        .build();
+    verify_with_preflight(context, client, &tx).unwrap_err();
+    client.preflight(&tx, &resolved_cells(context, &tx))
+}
+
+#[test]
+fn test_preflight_global_registry() {
+    let mut context = Context::default();
+    let (gr_code, lw_code) = deploy_registry(&mut context, ScriptHashType::Data1);
+    let input = always_success_input(&mut context);
+    let (mut client, tx) = RegistryClient::init(gr_code, lw_code, input).expect("init tx");
+    let tx = context.complete_tx(tx);
+
+    // the sentinel is created twice
+    let errors = preflight_after(&context, &client, &tx, |mut outputs, mut data| {
+        outputs.push(outputs[0].clone());
+        data.push(data[0].clone());
+        (outputs, data)
+    });
+    assert_eq!(
+        errors,
+        vec![PreflightError::InvalidInitOutputCount { count: 2 }]
+    );
+
+    // the sentinel doesn't cover the whole key space
+    let errors = preflight_after(&context, &client, &tx, |outputs, mut data| {
+        data[0] = registry_node([200u8; 32], [0u8; 32]);
+        (outputs, data)
+    });
+    assert_eq!(
+        errors,
+        vec![PreflightError::InvalidSentinelEnd {
+            output: 0,
+            end: [200u8; 32]
+        }]
+    );
+
+    // the registry is created without inputs, which the node rejects before running the scripts, global-registry
+    // would fail to load the first input
+    let no_inputs_tx = tx.as_advanced_builder().set_inputs(vec![]).build();
+    let errors = client.preflight(&no_inputs_tx, &resolved_cells(&context, &no_inputs_tx));
+    assert_eq!(errors, vec![PreflightError::MissingInitInput]);
+    assert_eq!(errors[0].error_info().unwrap().name, "IndexOutOfBound");
+    println!("{}", errors[0]);
+
+    verify_with_preflight(&context, &client, &tx).expect("pass verification");
+    commit_tx(&mut context, &mut client, &tx);
+
+    // the inserted node starts after the end of the head node
+    let key = [100u8; 32];
+    let tx = context.complete_tx(client.insert_key(key).expect("insert tx"));
+    let errors = preflight_after(&context, &client, &tx, |mut outputs, data| {
+        outputs[1] = outputs[1]
+            .clone()
+            .as_builder()
+            .lock(client.lock_script([120u8; 32]))
+            .build();
+        (outputs, data)
+    });
+    assert_eq!(
+        errors,
+        vec![PreflightError::UnexpectedOutputStart {
+            output: 1,
+            start: [120u8; 32],
+            expected: key
+        }]
+    );
+    println!("{}", errors[0]);
+
+    // the keys after the inserted one are dropped
+    let errors = preflight_after(&context, &client, &tx, |mut outputs, mut data| {
+        outputs.truncate(1);
+        data.truncate(1);
+        (outputs, data)
+    });
+    assert_eq!(errors, vec![PreflightError::MissingOutput { start: key }]);
+
+    // the head node keeps the lock group of the sentinel, it can't be duplicated
+    let errors = preflight_after(&context, &client, &tx, |mut outputs, mut data| {
+        outputs.push(outputs[0].clone());
+        data.push(registry_node(KEY_MAX, [0u8; 32]));
+        (outputs, data)
+    });
+    assert_eq!(
+        errors,
+        vec![
+            PreflightError::InvalidOutputCount { input: 0, count: 2 },
+            PreflightError::UnexpectedOutputStart {
+                output: 2,
+                start: KEY_MIN,
+                expected: key
+            }
+        ]
+    );
+
+    verify_with_preflight(&context, &client, &tx).expect("pass verification");
+
+    // the cells of the transaction must be resolved
+    let errors = client.preflight(&tx, &[]);
+    assert_eq!(errors.len(), 1 + tx.cell_deps().len());
+    assert_eq!(errors[0], PreflightError::UnresolvedCell(CellRef::Input(0)));
+}
+
+#[test]
+fn test_decode_exit_code() {
+    // the ranges of the contracts are disjoint
+    for code in i8::MIN..=i8::MAX {
+        let contracts = Contract::ALL
+            .into_iter()
+            .filter(|contract| contract.codes().contains(&code))
+            .count();
+        assert!(contracts <= 1, "code {} is in {} ranges", code, contracts);
+    }
+
+    let info = decode_exit_code(Contract::GlobalRegistry, GR_INVALID_LINKED_LIST);
+    assert_eq!(info.name, "InvalidLinkedList");
+    let info = decode_exit_code(Contract::LockWrapper, LW_UNSUPPORTED_ARGV_VERSION);
+    assert_eq!(info.name, "UnsupportedArgvVersion");
+    assert_eq!(
+        Contract::LockWrapper.find_error("UnsupportedArgvVersion"),
+        Some(info)
+    );
+    assert_eq!(
+        Contract::of_exit_code(LW_INVALID_ARGS_LENGTH),
+        Some(Contract::LockWrapper)
+    );
+    println!("{}", info);
+
+    // the codes after the last error of a contract are unknown
+    assert_eq!(decode_exit_code(Contract::LockWrapper, 68).name, "Unknown");
+    assert_eq!(decode_exit_code(Contract::LockWrapper, 7).name, "Unknown");
+    assert_eq!(Contract::of_exit_code(7), None);
+}
+
+// the variants of the `Error` enum in the source of a contract, in the order of their codes
+fn error_variants(source: &str) -> Vec<&str> {
+    let start = source.find("pub enum Error {").expect("Error enum") + "pub enum Error {".len();
+    let end = start + source[start..].find('}').expect("end of Error enum");
+    source[start..end]
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(|line| line.split([' ', ',']).next().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_error_tables_match_contracts() {
+    let sources = [
+        (
+            Contract::GlobalRegistry,
+            include_str!("../../contracts/global-registry/src/error.rs"),
+        ),
+        (
+            Contract::LockWrapper,
+            include_str!("../../contracts/lock-wrapper/src/error.rs"),
+        ),
+        (
+            Contract::DemoScriptA,
+            include_str!("../../contracts/demo-script-a/src/error.rs"),
+        ),
+        (
+            Contract::DemoScriptB,
+            include_str!("../../contracts/demo-script-b/src/error.rs"),
+        ),
+    ];
+    for (contract, source) in sources {
+        let variants = error_variants(source);
+        assert!(variants.len() > 4, "no errors of {}", contract);
+        for (index, variant) in variants.iter().enumerate() {
+            let code = contract.codes().start + index as i8;
+            let info = decode_exit_code(contract, code);
+            assert_eq!(info.name, *variant, "{} error {}", contract, code);
+            assert_ne!(info.description, "", "{} error {}", contract, code);
+        }
+        // the table has no entry past the last variant
+        let code = contract.codes().start + variants.len() as i8;
+        assert_eq!(
+            decode_exit_code(contract, code).name,
+            "Unknown",
+            "{} error {}",
+            contract,
+            code
+        );
+    }
+}
+
+// the native builds of global-registry and lock-wrapper step through a split, whose lock-wrapper group keeps the
+// config so no wrapped script runs. The native runs can't exec or spawn the wrapped scripts nor run the type group
+// of an initialization, and their exit codes are ignored, so the other tests run in the VM only
+#[cfg(feature = "native-simulator")]
+#[test]
+fn test_native_simulator() {
+    let mut lw = LockWrapperContext::new();
+    lw.registry_cell(KEY_MIN, registry_node(KEY_MAX, NO_OVERRIDE));
+    let tx = lw.client.insert_key([100u8; 32]).expect("insert tx");
+    let tx = lw.context.complete_tx(tx);
+    verify_with_preflight(&lw.context, &lw.client, &tx).expect("pass verification");
+
+    let loader = Loader::default();
+    loader.set_simulator(&mut lw.context, "global-registry");
+    loader.set_simulator(&mut lw.context, "lock-wrapper");
+    lw.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass native verification");
 }